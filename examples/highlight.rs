@@ -0,0 +1,90 @@
+//! Prints a Wren file with its tokens coloured for the terminal.
+//!
+//! ```text
+//! cargo run --example highlight -- path/to/script.wren --color
+//! ```
+//!
+//! Without `--color` the source is printed as it is.
+
+use rusty_wren::lex::{CypherLexer, Token};
+use std::{env, fs, process};
+
+const RESET: &str = "\x1b[0m";
+const KEYWORD: &str = "\x1b[1;34m";
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[33m";
+const ID: &str = "\x1b[37m";
+const OPERATOR: &str = "\x1b[36m";
+const COMMENT: &str = "\x1b[90m";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let color = args.iter().any(|a| a == "--color");
+    let path = match args.iter().find(|a| !a.starts_with("--")) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: highlight <file.wren> [--color]");
+            process::exit(2);
+        }
+    };
+    let source = fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("can't read {}: {}", path, e);
+        process::exit(1);
+    });
+    if !color {
+        print!("{}", source);
+        return;
+    }
+    match CypherLexer::new(&source) {
+        Ok(lexer) => print!("{}", highlight(&source, &lexer)),
+        Err(e) => {
+            eprintln!("{:?} at {:?}", e, e.location(&source));
+            process::exit(1);
+        }
+    }
+}
+
+fn highlight(source: &str, lexer: &CypherLexer) -> String {
+    let mut res = String::new();
+    let mut last = 0;
+    for (token, span) in lexer.tokens() {
+        res.push_str(&gap(&source[last..span.start]));
+        let text = &source[span.clone()];
+        match colour(token) {
+            Some(c) => res.push_str(&format!("{}{}{}", c, text, RESET)),
+            None => res.push_str(text),
+        }
+        last = span.end;
+    }
+    res.push_str(&gap(&source[last..]));
+    res
+}
+
+fn colour(token: &Token) -> Option<&'static str> {
+    match token {
+        t if t.is_keyword() => Some(KEYWORD),
+        Token::StringLit(_) | Token::CharLit(_) | Token::TextBlock(_) => Some(STRING),
+        Token::Digit(_) => Some(NUMBER),
+        Token::Id(_) => Some(ID),
+        t if t.is_operator() => Some(OPERATOR),
+        _ => None,
+    }
+}
+
+// The lexer skips comments, so they are found again in the text between two tokens.
+fn gap(text: &str) -> String {
+    let mut res = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("//").into_iter().chain(rest.find("/*")).min() {
+        let end = if rest[start..].starts_with("//") {
+            rest[start..].find('\n').map_or(rest.len(), |i| start + i)
+        } else {
+            rest[start..].find("*/").map_or(rest.len(), |i| start + i + 2)
+        };
+        res.push_str(&rest[..start]);
+        res.push_str(&format!("{}{}{}", COMMENT, &rest[start..end], RESET));
+        rest = &rest[end..];
+    }
+    res.push_str(rest);
+    res
+}
@@ -0,0 +1,54 @@
+//! Parser for the [Wren](https://wren.io/) language.
+//!
+//! The parsing entry points live in [`parse`] and the syntax tree in [`ast`].
+//! With the default `wren_ast` feature the syntax tree is re-exported from the crate root
+//! as well, e.g. `rusty_wren::Script`:
+//!
+//! ```
+//! use rusty_wren::ast::{Script, Unit};
+//! use rusty_wren::parse::parse_script;
+//!
+//! let script: Script = parse_script("class Tree { construct new() {} }").unwrap();
+//! assert!(matches!(script.units[0], Unit::Class(_)));
+//! ```
+//!
+//! The parser can also be driven directly, starting from any token position:
+//!
+//! ```
+//! use rusty_wren::parse::{CypherParser, ParseResult};
+//!
+//! let parser = CypherParser::new("a + 1").unwrap();
+//! assert!(matches!(parser.expression(0), ParseResult::Success(_, 3)));
+//! ```
+//!
+//! The tokens and their spans are available through [`lex`]; `examples/highlight.rs`
+//! uses them to colour a script for the terminal.
+
+#[macro_use]
+pub(crate) mod parser;
+
+pub mod ast {
+    pub use crate::parser::ast::*;
+}
+
+#[cfg(feature = "wren_ast")]
+pub use ast::*;
+
+pub mod analysis {
+    pub use crate::parser::analysis::*;
+    pub use crate::parser::visitor::{depth, walk, walk_mut, Node, NodeMut};
+}
+
+pub mod lex {
+    pub use crate::parser::lexer::{CypherLexer, Token, TokenKind};
+}
+
+pub mod parse {
+    pub use crate::parser::diagnostic::{DiagnosticCollector, ParseDiagnostic, SourceMap};
+    pub use crate::parser::parser::CypherParser;
+    pub use crate::parser::result::ParseResult;
+    pub use crate::parser::{
+        parse_classes, parse_expression_list, parse_functions, parse_non_empty_expression_list,
+        parse_script, parse_to_completion, ParseError, ParseErrorKind, SourceId,
+    };
+}
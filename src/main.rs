@@ -1,6 +1,3 @@
-#[macro_use]
-mod parser;
-
 fn main() {
 println!("")
 }
@@ -0,0 +1,584 @@
+use crate::parser::ast::*;
+use crate::parser::lexer::CypherLexer;
+use crate::parser::parser::CypherParser;
+use crate::parser::result::ParseResult;
+use crate::parser::visitor::{walk, Node};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CycleError<'a> {
+    pub class: &'a str,
+}
+
+pub fn superclass_chain<'a>(
+    script: &Script<'a>,
+    class_name: &str,
+) -> Result<Vec<&'a str>, CycleError<'a>> {
+    let mut chain: Vec<&'a str> = vec![];
+    let mut current = script.get_class(class_name);
+
+    while let Some(parent) = current.and_then(|c| c.inherit) {
+        if parent.value == class_name || chain.contains(&parent.value) {
+            return Err(CycleError {
+                class: parent.value,
+            });
+        }
+        chain.push(parent.value);
+        current = script.get_class(parent.value);
+    }
+    Ok(chain)
+}
+
+// Classes are the roots when their parent isn't defined in the script (`Object` or a class
+// from an import). Classes in an inheritance cycle aren't reachable from a root and are left out.
+#[derive(Debug)]
+pub struct ClassHierarchy<'s, 'a> {
+    pub roots: Vec<HierarchyNode<'s, 'a>>,
+}
+
+#[derive(Debug)]
+pub struct HierarchyNode<'s, 'a> {
+    pub class: &'s ClassDefinition<'a>,
+    pub children: Vec<HierarchyNode<'s, 'a>>,
+}
+
+pub fn class_hierarchy<'s, 'a>(script: &'s Script<'a>) -> ClassHierarchy<'s, 'a> {
+    let classes: Vec<&'s ClassDefinition<'a>> = script
+        .units
+        .iter()
+        .filter_map(|u| match u {
+            Unit::Class(c) => Some(c),
+            _ => None,
+        })
+        .collect();
+    fn node<'s, 'a>(
+        class: &'s ClassDefinition<'a>,
+        classes: &[&'s ClassDefinition<'a>],
+    ) -> HierarchyNode<'s, 'a> {
+        let children = classes
+            .iter()
+            .filter(|c| c.inherit.is_some_and(|p| p.value == class.name.value))
+            .map(|c| node(c, classes))
+            .collect();
+        HierarchyNode { class, children }
+    }
+    let is_defined = |name: &str| classes.iter().any(|c| c.name.value == name);
+    let roots = classes
+        .iter()
+        .filter(|c| c.inherit.is_none_or(|p| !is_defined(p.value)))
+        .map(|c| node(c, &classes))
+        .collect();
+    ClassHierarchy { roots }
+}
+
+impl<'s, 'a> ClassHierarchy<'s, 'a> {
+    // A root is at depth 0, as is a class that isn't in the hierarchy.
+    pub fn depth_of(&self, name: &str) -> usize {
+        self.path_to(name).map_or(0, |path| path.len() - 1)
+    }
+
+    // The parent first, up to the root.
+    pub fn ancestors_of(&self, name: &str) -> Vec<&'a str> {
+        let mut path = self.path_to(name).unwrap_or_default();
+        path.pop();
+        path.reverse();
+        path
+    }
+
+    fn path_to(&self, name: &str) -> Option<Vec<&'a str>> {
+        fn find<'a>(node: &HierarchyNode<'_, 'a>, name: &str, path: &mut Vec<&'a str>) -> bool {
+            path.push(node.class.name.value);
+            if node.class.name.value == name || node.children.iter().any(|c| find(c, name, path)) {
+                return true;
+            }
+            path.pop();
+            false
+        }
+        let mut path = vec![];
+        self.roots
+            .iter()
+            .any(|r| find(r, name, &mut path))
+            .then_some(path)
+    }
+}
+
+impl<'s, 'a> fmt::Display for ClassHierarchy<'s, 'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write(f: &mut fmt::Formatter<'_>, node: &HierarchyNode, level: usize) -> fmt::Result {
+            writeln!(f, "{}{}", "  ".repeat(level), node.class.name.value)?;
+            node.children
+                .iter()
+                .try_for_each(|c| write(f, c, level + 1))
+        }
+        self.roots.iter().try_for_each(|r| write(f, r, 0))
+    }
+}
+
+// Edges go from the parent to the child; a parent defined elsewhere (`Object`)
+// still gets its edge and is drawn with the default shape. Foreign classes are ellipses.
+pub fn to_dot(script: &Script<'_>) -> String {
+    let mut res = String::from("digraph classes {\n    node [shape=box];\n");
+    let classes = script.units.iter().filter_map(|u| match u {
+        Unit::Class(c) => Some(c),
+        _ => None,
+    });
+    for c in classes.clone() {
+        let shape = if c.foreign { " [shape=ellipse]" } else { "" };
+        res.push_str(&format!("    \"{}\"{};\n", c.name.value, shape));
+    }
+    for c in classes {
+        if let Some(parent) = c.inherit {
+            res.push_str(&format!("    \"{}\" -> \"{}\";\n", parent.value, c.name.value));
+        }
+    }
+    res.push_str("}\n");
+    res
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SemanticError<'a> {
+    SelfInheritance(&'a str),
+    DuplicateSignature { signature: String, is_static: bool },
+    AnonymousConstructor,
+}
+
+// Static and instance methods live in separate tables, so the same signature may appear once in each.
+pub fn validate_class_def<'a>(cls: &ClassDefinition<'a>) -> Vec<SemanticError<'a>> {
+    let mut errors = vec![];
+    if cls.inherit.is_some_and(|parent| parent.value == cls.name.value) {
+        errors.push(SemanticError::SelfInheritance(cls.name.value));
+    }
+
+    let mut seen: Vec<(bool, String)> = vec![];
+    for unit in &cls.elems {
+        if let ClassStatement::Constructor(id, _, _) = &unit.statement {
+            if id.value.is_empty() {
+                errors.push(SemanticError::AnonymousConstructor);
+            }
+        }
+        let key = (unit.tpe.is_static(), unit.statement.signature());
+        if seen.contains(&key) {
+            let (is_static, signature) = key;
+            errors.push(SemanticError::DuplicateSignature {
+                signature,
+                is_static,
+            });
+        } else {
+            seen.push(key);
+        }
+    }
+    errors
+}
+
+// The continuation of the negated `x logic`, for a single operand `x` that is negated as well
+// when the result starts with `&&` or `||`. `None` when `x` would have to be grouped with
+// what follows it, e.g. for `x || a && b`.
+pub fn negate<'a>(logic: Logic<'a>) -> Option<Logic<'a>> {
+    let expr = Expression::Compound(
+        Box::new(Expression::E),
+        Box::new(CompoundExpression::Logic(logic)),
+    );
+    match de_morgan(&expr)? {
+        Expression::Compound(lhs, tail) => match (*lhs, *tail) {
+            (Expression::E, CompoundExpression::Logic(l)) => Some(l),
+            (Expression::Not(e), CompoundExpression::Logic(l)) if *e == Expression::E => Some(l),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Applies De Morgan's laws, reading a logic chain with Wren's precedence: `||` is looser than
+// `&&`, which is looser than the comparisons. When the negation can't be written without
+// parentheses the parser would drop, the whole expression is wrapped in `!` instead.
+// `!x` is turned back into `x` only when negating `x` gives `!x` again,
+// so that negating twice returns the original expression.
+pub fn negate_expression<'a>(expr: Expression<'a>) -> Expression<'a> {
+    match expr {
+        Expression::Not(e) if wraps(&e) => *e,
+        e => de_morgan(&e).unwrap_or_else(|| Expression::Not(Box::new(e))),
+    }
+}
+
+pub fn contains_call_to(node: Node, name: &str) -> bool {
+    let mut found = false;
+    walk(node, |node| {
+        if let Node::Call(c) = node {
+            found |= c.id.value == name;
+        }
+        !found
+    });
+    found
+}
+
+// A conservative approximation: only an expression that calls nothing is taken to be free of
+// side effects. Reading a variable counts as a call, it may be a getter. Operators on values
+// are treated as pure, though in Wren they are method calls too.
+pub fn is_effectful(stmt: &Statement) -> bool {
+    match stmt {
+        Statement::Expression(e) => {
+            let mut effect = false;
+            walk(Node::Expression(e), |node| {
+                effect |= matches!(
+                    node,
+                    Node::Call(_)
+                        | Node::Atom(
+                            AtomExpression::ImportModule(_)
+                                | AtomExpression::Break
+                                | AtomExpression::Continue
+                        )
+                );
+                !effect
+            });
+            effect
+        }
+        _ => true,
+    }
+}
+
+// Expression statements whose value is dropped and that have no effect, e.g. `1 + 2`.
+// A block holding a single expression returns it, so that one is not reported.
+pub fn useless_expressions<'s, 'a>(script: &'s Script<'a>) -> Vec<&'s Expression<'a>> {
+    let mut res = vec![];
+    let mut check = |stmt: &'s Statement<'a>| {
+        if let Statement::Expression(e) = stmt {
+            if !is_effectful(stmt) {
+                res.push(e)
+            }
+        }
+    };
+    walk(Node::Script(script), |node| {
+        match node {
+            Node::Unit(Unit::Statement(s)) => check(s),
+            Node::Block(b) if b.statements.len() > 1 => b.statements.iter().for_each(&mut check),
+            _ => (),
+        }
+        true
+    });
+    res
+}
+
+// In pre-order, and every link of a chain is a call of its own:
+// `Tree.new(1)` yields the `Tree` call and then its `new` tail.
+pub fn walk_calls<'s, 'a>(script: &'s Script<'a>) -> impl Iterator<Item = &'s Call<'a>> {
+    let mut calls = vec![];
+    walk(Node::Script(script), |node| {
+        if let Node::Call(c) = node {
+            calls.push(c)
+        }
+        true
+    });
+    calls.into_iter()
+}
+
+// A variable is the head of a call chain that takes no arguments (`x`, `x.y`, `x[0]`).
+// Names are returned once, in the order they first appear.
+pub fn variables_referenced<'s, 'a>(expr: &'s Expression<'a>) -> Vec<&'a str> {
+    let mut vars = vec![];
+    let mut add = |call: &Call<'a>| {
+        if call.middle == BlockOrEnum::None && !vars.contains(&call.id.value) {
+            vars.push(call.id.value)
+        }
+    };
+    walk(Node::Expression(expr), |node| {
+        match node {
+            Node::Atom(AtomExpression::Call(c))
+            | Node::Atom(AtomExpression::CollectionElem(c, _)) => add(c),
+            Node::Atom(AtomExpression::Range(r)) => {
+                for e in [&r.left, &r.right] {
+                    if let RangeExpression::Call(c) = e {
+                        add(c)
+                    }
+                }
+            }
+            _ => (),
+        }
+        true
+    });
+    vars
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ScriptMetrics {
+    pub total_classes: usize,
+    pub total_methods: usize,
+    pub total_constructors: usize,
+    pub total_statements: usize,
+    pub total_expressions: usize,
+    pub max_nesting_depth: usize,
+}
+
+// Every class element but a constructor counts as a method, getters and operators included.
+// Expressions are counted with their subexpressions; the depth is the one of `visitor::depth`.
+pub fn metrics(script: &Script<'_>) -> ScriptMetrics {
+    let mut res = ScriptMetrics::default();
+    let mut stack = vec![(Node::Script(script), 0)];
+    while let Some((node, depth)) = stack.pop() {
+        let depth = if node.is_nesting() { depth + 1 } else { depth };
+        res.max_nesting_depth = res.max_nesting_depth.max(depth);
+        match node {
+            Node::Class(_) => res.total_classes += 1,
+            Node::ClassUnit(u) => match u.statement {
+                ClassStatement::Constructor(..) => res.total_constructors += 1,
+                _ => res.total_methods += 1,
+            },
+            Node::Statement(_) => res.total_statements += 1,
+            Node::Expression(_) => res.total_expressions += 1,
+            _ => (),
+        }
+        stack.extend(node.children().into_iter().map(|c| (c, depth)));
+    }
+    res
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ComplexityMetrics {
+    pub statements: usize,
+    pub branches: usize,
+    pub max_depth: usize,
+}
+
+impl ComplexityMetrics {
+    // McCabe's number: one path through the body plus one per branch.
+    pub fn cyclomatic(&self) -> usize {
+        self.branches + 1
+    }
+}
+
+// A function without a body (a foreign one) has nothing to measure.
+pub fn complexity(function: &Function<'_>) -> ComplexityMetrics {
+    function.block.as_ref().map(block_complexity).unwrap_or_default()
+}
+
+// Every `if`, `else if`, `while` and `for` is a branch, closures included;
+// the depth counts the blocks on the deepest path, `block` being the first.
+pub fn block_complexity(block: &Block<'_>) -> ComplexityMetrics {
+    let mut res = ComplexityMetrics::default();
+    let mut stack = vec![(Node::Block(block), 0)];
+    while let Some((node, depth)) = stack.pop() {
+        let depth = if let Node::Block(_) = node { depth + 1 } else { depth };
+        res.max_depth = res.max_depth.max(depth);
+        match node {
+            Node::Statement(_) => res.statements += 1,
+            Node::If(i) => res.branches += 1 + i.others.len(),
+            Node::While(_) | Node::For(_) => res.branches += 1,
+            _ => (),
+        }
+        stack.extend(node.children().into_iter().map(|c| (c, depth)));
+    }
+    res
+}
+
+// Comments are skipped by the lexer, so they are recovered from the source:
+// the `//` lines directly above the line the unit starts on.
+pub fn extract_doc_comment<'a>(
+    units: &[Unit<'a>],
+    target_index: usize,
+    source: &'a str,
+) -> Option<&'a str> {
+    let offset = *unit_offsets(units, source)?.get(target_index)?;
+    doc_comment_before(source, offset)
+}
+
+pub fn extract_doc_comments<'a>(units: &[Unit<'a>], source: &'a str) -> Vec<Option<&'a str>> {
+    match unit_offsets(units, source) {
+        Some(offsets) => offsets
+            .into_iter()
+            .map(|offset| doc_comment_before(source, offset))
+            .collect(),
+        None => vec![None; units.len()],
+    }
+}
+
+// Whether `negate_expression` only wraps `expr` in `!`.
+fn wraps(expr: &Expression) -> bool {
+    match expr {
+        Expression::Not(e) if wraps(e) => false,
+        e => de_morgan(e).is_none(),
+    }
+}
+
+// The operands and operators of `a op b op c ...`, which the parser nests to the right.
+type Chain<'a> = (Vec<Expression<'a>>, Vec<LogicOp>);
+
+fn chain<'a>(expr: &Expression<'a>) -> Chain<'a> {
+    let mut operands = vec![];
+    let mut ops = vec![];
+    let mut expr = expr;
+    while let Expression::Compound(lhs, tail) = expr {
+        match tail.as_ref() {
+            CompoundExpression::Logic(Logic::Atom(op, rhs)) => {
+                operands.push(lhs.as_ref().clone());
+                ops.push(op.clone());
+                expr = rhs;
+            }
+            _ => break,
+        }
+    }
+    operands.push(expr.clone());
+    (operands, ops)
+}
+
+// The first operand is the left-hand side of a compound expression, so it may be a whole chain:
+// that is how `(a || b) && c` is kept apart from `a || b && c`.
+fn unchain<'a>((mut operands, ops): Chain<'a>) -> Expression<'a> {
+    let mut expr = operands.pop().unwrap_or(Expression::E);
+    for (lhs, op) in operands.into_iter().zip(ops).rev() {
+        expr = Expression::Compound(
+            Box::new(lhs),
+            Box::new(CompoundExpression::Logic(Logic::Atom(op, expr))),
+        );
+    }
+    expr
+}
+
+fn split<'a>((operands, ops): Chain<'a>, sep: LogicOp) -> Vec<Chain<'a>> {
+    let mut parts: Vec<Chain<'a>> = vec![(vec![], vec![])];
+    let mut ops = ops.into_iter();
+    for operand in operands {
+        if let Some(part) = parts.last_mut() {
+            part.0.push(operand);
+        }
+        match ops.next() {
+            Some(op) if op == sep => parts.push((vec![], vec![])),
+            Some(op) => {
+                if let Some(part) = parts.last_mut() {
+                    part.1.push(op)
+                }
+            }
+            None => {}
+        }
+    }
+    parts
+}
+
+fn join<'a>(parts: Vec<Chain<'a>>, sep: LogicOp) -> Chain<'a> {
+    let mut res: Chain<'a> = (vec![], vec![]);
+    for (i, (operands, ops)) in parts.into_iter().enumerate() {
+        if i > 0 {
+            res.1.push(sep.clone());
+        }
+        res.0.extend(operands);
+        res.1.extend(ops);
+    }
+    res
+}
+
+fn de_morgan<'a>(expr: &Expression<'a>) -> Option<Expression<'a>> {
+    let chain = chain(expr);
+    if chain.1.is_empty() || !chain.0[1..].iter().all(closed) {
+        return None;
+    }
+    let disjuncts = split(chain, LogicOp::Or);
+    let single = disjuncts.len() == 1;
+    let mut conjuncts = vec![];
+    for (i, disjunct) in disjuncts.into_iter().enumerate() {
+        let terms = split(disjunct, LogicOp::And)
+            .into_iter()
+            .map(negate_term)
+            .collect::<Option<Vec<_>>>()?;
+        let negated = join(terms, LogicOp::Or);
+        if single || !negated.1.contains(&LogicOp::Or) {
+            conjuncts.push(negated);
+        } else if i == 0 {
+            conjuncts.push((vec![unchain(negated)], vec![]));
+        } else {
+            return None;
+        }
+    }
+    let negated = join(conjuncts, LogicOp::And);
+    if negated.0[1..].iter().all(closed) {
+        Some(unchain(negated))
+    } else {
+        None
+    }
+}
+
+fn negate_term<'a>((operands, ops): Chain<'a>) -> Option<Chain<'a>> {
+    match (operands.as_slice(), ops.as_slice()) {
+        ([_, _], [op]) => Some((operands, vec![negate_comparison(op.clone())])),
+        ([e @ Expression::Not(_)], []) => Some((vec![negate_expression(e.clone())], vec![])),
+        ([e], []) => Some(match de_morgan(e) {
+            Some(negated) => chain(&negated),
+            None => (vec![Expression::Not(Box::new(e.clone()))], vec![]),
+        }),
+        _ => None,
+    }
+}
+
+// An operand without a logic operator, `is` or `?:` of its own, so it binds tighter than
+// the chain it sits in. Calls and collections are left alone, their arguments are enclosed.
+fn closed(expr: &Expression) -> bool {
+    match expr {
+        Expression::Compound(_, tail) => {
+            let mut open = false;
+            walk(Node::Compound(tail), |node| match node {
+                Node::Compound(
+                    CompoundExpression::Logic(_)
+                    | CompoundExpression::Is(_)
+                    | CompoundExpression::Elvis(_),
+                ) => {
+                    open = true;
+                    false
+                }
+                Node::Atom(_) => false,
+                _ => !open,
+            });
+            !open
+        }
+        _ => true,
+    }
+}
+
+fn negate_comparison(op: LogicOp) -> LogicOp {
+    match op {
+        LogicOp::Gt => LogicOp::Le,
+        LogicOp::Le => LogicOp::Gt,
+        LogicOp::Lt => LogicOp::Ge,
+        LogicOp::Ge => LogicOp::Lt,
+        LogicOp::Eq => LogicOp::NotEq,
+        LogicOp::NotEq => LogicOp::Eq,
+        LogicOp::And => LogicOp::Or,
+        LogicOp::Or => LogicOp::And,
+    }
+}
+
+// Byte offsets where the units start, provided the source parses to exactly these units.
+fn unit_offsets(units: &[Unit], source: &str) -> Option<Vec<usize>> {
+    let lexer = CypherLexer::new(source).ok()?;
+    let spans = lexer.spans.clone();
+    let parser = CypherParser::from_lexer(lexer);
+    let mut offsets = vec![];
+    let mut pos = 0;
+    for unit in units {
+        match parser.file_unit(pos) {
+            ParseResult::Success(parsed, next) if &parsed == unit => {
+                offsets.push(spans.get(pos)?.start);
+                pos = next;
+            }
+            _ => return None,
+        }
+    }
+    Some(offsets)
+}
+
+fn doc_comment_before(source: &str, offset: usize) -> Option<&str> {
+    let mut line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+    if !source[line_start..offset].trim().is_empty() {
+        return None;
+    }
+    let mut block: Option<(usize, usize)> = None;
+    while line_start > 0 {
+        let prev_end = line_start - 1;
+        let prev_start = source[..prev_end].rfind('\n').map_or(0, |i| i + 1);
+        let line = &source[prev_start..prev_end];
+        if !line.trim_start().starts_with("//") {
+            break;
+        }
+        let start = prev_start + line.len() - line.trim_start().len();
+        let end = prev_start + line.trim_end().len();
+        block = Some((start, block.map_or(end, |(_, e)| e)));
+        line_start = prev_start;
+    }
+    block.map(|(start, end)| &source[start..end])
+}
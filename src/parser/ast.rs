@@ -1,3 +1,7 @@
+use crate::parser::util::{strip_char_quotes, strip_quotes};
+use crate::parser::visitor::{depth, walk, Node};
+use crate::parser::{analysis, printer, transform};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::iter::Map;
 
@@ -12,12 +16,91 @@ pub enum Number {
     Binary(isize),
 }
 
+impl Number {
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            Number::Int(v) | Number::Hex(v) => *v as f64,
+            Number::Float(v) => *v,
+            Number::Binary(v) => *v as f64,
+        }
+    }
+
+    pub fn to_wren_literal(&self) -> Result<String, String> {
+        match self {
+            Number::Int(v) => Ok(v.to_string()),
+            Number::Float(v) if !v.is_finite() => Err(format!("{} has no wren literal", v)),
+            Number::Float(v) => {
+                let literal = format!("{:?}", v);
+                match literal.split_once('e') {
+                    Some((m, e)) if !m.contains('.') => Ok(format!("{}.0e{}", m, e)),
+                    _ => Ok(literal),
+                }
+            }
+            Number::Hex(v) if *v < 0 => Ok(format!("-0x{:x}", v.unsigned_abs())),
+            Number::Hex(v) => Ok(format!("0x{:x}", v)),
+            Number::Binary(v) if *v < 0 => Err(format!("{} has no binary literal", v)),
+            Number::Binary(v) => Ok(format!("0b{:b}", v)),
+        }
+    }
+
+    pub fn checked_add(&self, rhs: &Number) -> Option<Number> {
+        self.checked(rhs, i64::checked_add, |a, b| a + b)
+    }
+
+    pub fn checked_sub(&self, rhs: &Number) -> Option<Number> {
+        self.checked(rhs, i64::checked_sub, |a, b| a - b)
+    }
+
+    pub fn checked_mul(&self, rhs: &Number) -> Option<Number> {
+        self.checked(rhs, i64::checked_mul, |a, b| a * b)
+    }
+
+    // Wren numbers are doubles, so `7 / 2` is `3.5` rather than a truncated integer.
+    pub fn checked_div(&self, rhs: &Number) -> Option<Number> {
+        match (self.as_i64(), rhs.as_i64()) {
+            (Some(_), Some(0)) => None,
+            (Some(a), Some(b)) if a.checked_rem(b).is_some_and(|r| r != 0) => {
+                Some(Number::Float(a as f64 / b as f64))
+            }
+            _ => self.checked(rhs, i64::checked_div, |a, b| a / b),
+        }
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        match self {
+            Number::Int(v) | Number::Hex(v) => Some(*v),
+            Number::Binary(v) => i64::try_from(*v).ok(),
+            Number::Float(_) => None,
+        }
+    }
+
+    // A float on either side gives a float, two integers of the same notation keep it
+    // and mixed integers give an `Int`.
+    fn checked<I, F>(&self, rhs: &Number, int_op: I, float_op: F) -> Option<Number>
+    where
+        I: FnOnce(i64, i64) -> Option<i64>,
+        F: FnOnce(f64, f64) -> f64,
+    {
+        let (a, b) = match (self.as_i64(), rhs.as_i64()) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return Some(Number::Float(float_op(self.as_f64(), rhs.as_f64()))),
+        };
+        let v = int_op(a, b)?;
+        match (self, rhs) {
+            (Number::Hex(_), Number::Hex(_)) => Some(Number::Hex(v)),
+            (Number::Binary(_), Number::Binary(_)) => isize::try_from(v).ok().map(Number::Binary),
+            _ => Some(Number::Int(v)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum AtomExpression<'a> {
     Null,
     Bool(bool),
     CharLit(&'a str),
     StringLit(&'a str),
+    TextBlock(&'a str),
     Number(Number),
     MapInit(Vec<(Expression<'a>, Expression<'a>)>),
     ListInit(Enumeration<'a>),
@@ -30,13 +113,55 @@ pub enum AtomExpression<'a> {
     Sub(Box<AtomExpression<'a>>),
 }
 
+impl<'a> From<Number> for AtomExpression<'a> {
+    fn from(n: Number) -> Self {
+        AtomExpression::Number(n)
+    }
+}
+
+impl<'a> From<bool> for AtomExpression<'a> {
+    fn from(v: bool) -> Self {
+        AtomExpression::Bool(v)
+    }
+}
+
+impl<'a> From<&'a str> for AtomExpression<'a> {
+    fn from(v: &'a str) -> Self {
+        AtomExpression::StringLit(v)
+    }
+}
+
 impl<'a> AtomExpression<'a> {
     pub fn string_or_default(&self, default: &'a str) -> &'a str {
+        self.as_str().unwrap_or(default)
+    }
+
+    pub fn as_number(&self) -> Option<Number> {
+        match self {
+            AtomExpression::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
         match self {
-            AtomExpression::StringLit(v) | AtomExpression::CharLit(v) => v,
-            _ => default,
+            AtomExpression::Bool(b) => Some(*b),
+            _ => None,
         }
     }
+
+    // The literal without its quotes.
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self {
+            AtomExpression::StringLit(v) | AtomExpression::TextBlock(v) => Some(strip_quotes(v)),
+            AtomExpression::CharLit(v) => Some(strip_char_quotes(v)),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, AtomExpression::Null)
+    }
 }
 
 #[derive(Debug, Default, Clone, PartialEq)]
@@ -44,11 +169,32 @@ pub struct Params<'a> {
     pub ids: Vec<Id<'a>>,
 }
 
+impl<'a> Params<'a> {
+    pub fn names<'s>(&'s self) -> impl Iterator<Item = &'a str> + 's {
+        self.ids.iter().map(|id| id.value)
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Id<'a> {
     pub value: &'a str,
 }
 
+// `_field` is an instance field and `__field` a static one, anything else is a public name.
+impl<'a> Id<'a> {
+    pub fn is_private(&self) -> bool {
+        self.value.starts_with('_') && !self.is_class_level()
+    }
+    pub fn is_class_level(&self) -> bool {
+        self.value.starts_with("__")
+    }
+    pub fn is_public(&self) -> bool {
+        self.value.starts_with(|c: char| c.is_alphabetic())
+    }
+}
+
+// Wren's conditional operator `cond ? lhs : rhs`. The condition is the left side
+// of the compound holding it; Wren has no null-coalescing `?:`.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Elvis<'a> {
     pub lhs: Expression<'a>,
@@ -64,6 +210,10 @@ impl<'a> Enumeration<'a> {
     pub fn new(values: Vec<Expression<'a>>) -> Self {
         Self { values }
     }
+
+    pub fn dedup_literals(&mut self) {
+        self.values.dedup_by(|a, b| a == b && a.is_literal())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -71,9 +221,71 @@ pub enum Expression<'a> {
     Atom(AtomExpression<'a>),
     Compound(Box<Expression<'a>>,Box<CompoundExpression<'a>>),
     Not(Box<Expression<'a>>),
+    // Placeholder that is never produced by the parser,
+    // used to take an expression out of the tree while it is being rewritten.
     E
 }
 
+impl<'a> From<AtomExpression<'a>> for Expression<'a> {
+    fn from(atom: AtomExpression<'a>) -> Self {
+        Expression::Atom(atom)
+    }
+}
+
+impl<'a> Expression<'a> {
+    pub fn depth(&self) -> usize {
+        depth(Node::Expression(self))
+    }
+
+    pub fn is_placeholder(&self) -> bool {
+        matches!(self, Expression::E)
+    }
+
+    pub fn is_literal(&self) -> bool {
+        matches!(
+            self,
+            Expression::Atom(
+                AtomExpression::Bool(_)
+                    | AtomExpression::Number(_)
+                    | AtomExpression::Null
+                    | AtomExpression::StringLit(_)
+                    | AtomExpression::TextBlock(_)
+                    | AtomExpression::CharLit(_)
+            )
+        )
+    }
+
+    pub fn simplify(self) -> Expression<'a> {
+        transform::simplify(self)
+    }
+
+    pub fn to_wren_source(&self) -> String {
+        printer::expression(self, 0)
+    }
+
+    pub fn variables_referenced(&self) -> Vec<&str> {
+        analysis::variables_referenced(self)
+    }
+
+    pub fn contains_call_to(&self, name: &str) -> bool {
+        analysis::contains_call_to(Node::Expression(self), name)
+    }
+
+    // The loosest operator along the chain of right operands, 15 when there is none.
+    pub fn loosest_precedence(&self) -> u8 {
+        let mut res = 15;
+        let mut current = self;
+        while let Expression::Compound(_, tail) = current {
+            res = res.min(tail.operator_precedence());
+            match tail.right_operand() {
+                Some(e) => current = e,
+                None => break,
+            }
+        }
+        res
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum CompoundExpression<'a>{
     Logic(Logic<'a>),
@@ -83,6 +295,66 @@ pub enum CompoundExpression<'a>{
     Elvis(Elvis<'a>)
 }
 
+impl<'a> CompoundExpression<'a> {
+    pub fn operator_precedence(&self) -> u8 {
+        match self {
+            CompoundExpression::Elvis(_) => 0,
+            CompoundExpression::Logic(Logic::Or(..))
+            | CompoundExpression::Logic(Logic::Atom(LogicOp::Or, _)) => 1,
+            CompoundExpression::Logic(Logic::And(..))
+            | CompoundExpression::Logic(Logic::Atom(LogicOp::And, _)) => 2,
+            CompoundExpression::Logic(Logic::Atom(_, _)) | CompoundExpression::Is(_) => 3,
+            CompoundExpression::Arith(a) => a.precedence(),
+            CompoundExpression::Tail(_) => 15,
+        }
+    }
+    pub fn is_left_associative(&self) -> bool {
+        match self {
+            CompoundExpression::Elvis(_) => false,
+            CompoundExpression::Arith(a) => a.associates_left(),
+            _ => true,
+        }
+    }
+
+    fn right_operand<'s>(&'s self) -> Option<&'s Expression<'a>> {
+        fn arith_operand<'s, 'a>(arith: &'s Arithmetic<'a>) -> &'s Expression<'a> {
+            match arith {
+                Arithmetic::Expression(e) | Arithmetic::Mul(_, e) => e,
+                Arithmetic::Add(_, a)
+                | Arithmetic::Range(_, a)
+                | Arithmetic::Shift(_, a)
+                | Arithmetic::Bit(_, a) => arith_operand(a),
+            }
+        }
+        match self {
+            CompoundExpression::Logic(Logic::Atom(_, e)) => Some(e),
+            CompoundExpression::Arith(a) => Some(arith_operand(a)),
+            CompoundExpression::Is(e) => Some(e),
+            CompoundExpression::Elvis(e) => Some(&e.rhs),
+            CompoundExpression::Logic(_) | CompoundExpression::Tail(_) => None,
+        }
+    }
+
+    pub(crate) fn right_operand_mut<'s>(&'s mut self) -> Option<&'s mut Expression<'a>> {
+        fn arith_operand<'s, 'a>(arith: &'s mut Arithmetic<'a>) -> &'s mut Expression<'a> {
+            match arith {
+                Arithmetic::Expression(e) | Arithmetic::Mul(_, e) => e,
+                Arithmetic::Add(_, a)
+                | Arithmetic::Range(_, a)
+                | Arithmetic::Shift(_, a)
+                | Arithmetic::Bit(_, a) => arith_operand(a),
+            }
+        }
+        match self {
+            CompoundExpression::Logic(Logic::Atom(_, e)) => Some(e),
+            CompoundExpression::Arith(a) => Some(arith_operand(a)),
+            CompoundExpression::Is(e) => Some(e),
+            CompoundExpression::Elvis(e) => Some(&mut e.rhs),
+            CompoundExpression::Logic(_) | CompoundExpression::Tail(_) => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Statement<'a> {
     Expression(Expression<'a>),
@@ -95,12 +367,84 @@ pub enum Statement<'a> {
     Return(Expression<'a>),
 }
 
+impl<'a> Statement<'a> {
+    pub fn depth(&self) -> usize {
+        depth(Node::Statement(self))
+    }
+
+    pub fn contains_call_to(&self, name: &str) -> bool {
+        analysis::contains_call_to(Node::Statement(self), name)
+    }
+
+    // Conservative, see `analysis::is_effectful`.
+    pub fn is_effectful(&self) -> bool {
+        analysis::is_effectful(self)
+    }
+
+    pub fn returns_value(&self) -> bool {
+        match self {
+            Statement::Return(_) => true,
+            Statement::Block(b) => b.returns_value(),
+            Statement::If(i) => i.always_returns(),
+            _ => false,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Block<'a> {
     pub params: Params<'a>,
     pub statements: Vec<Statement<'a>>,
 }
 
+impl<'a> Block<'a> {
+    pub fn param_names<'s>(&'s self) -> impl Iterator<Item = &'a str> + 's {
+        self.params.names()
+    }
+
+    pub fn depth(&self) -> usize {
+        depth(Node::Block(self))
+    }
+
+    pub fn count_statements(&self) -> usize {
+        analysis::block_complexity(self).statements
+    }
+
+    pub fn count_branches(&self) -> usize {
+        analysis::block_complexity(self).branches
+    }
+
+    pub fn max_depth(&self) -> usize {
+        analysis::block_complexity(self).max_depth
+    }
+
+    fn returns_value(&self) -> bool {
+        self.statements.iter().any(Statement::returns_value)
+    }
+
+    pub fn to_function(self, name: Id<'a>) -> Function<'a> {
+        Function {
+            name,
+            params: self.params,
+            block: Some(Block {
+                params: Params::default(),
+                statements: self.statements,
+            }),
+        }
+    }
+
+    pub fn is_single_expression(&self) -> bool {
+        self.as_single_expression().is_some()
+    }
+
+    pub fn as_single_expression(&self) -> Option<&Expression<'a>> {
+        match self.statements.as_slice() {
+            [Statement::Expression(e)] if self.params.ids.is_empty() => Some(e),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Call<'a> {
     pub id: Id<'a>,
@@ -116,12 +460,24 @@ impl<'a> Call<'a> {
             middle: BlockOrEnum::None,
         }
     }
+
+    // Only this segment of the chain counts: `a.b()` is a field access followed by a method call.
+    pub fn is_method_call(&self) -> bool {
+        !self.is_field_access()
+    }
+    pub fn is_field_access(&self) -> bool {
+        matches!(self.middle, BlockOrEnum::None)
+    }
+    pub fn callee_name(&self) -> &'a str {
+        self.id.value
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum BlockOrEnum<'a> {
     Block(Block<'a>),
     Enum(Enumeration<'a>),
+    // No arguments and no block: a field access or a getter, e.g. `list.count`.
     None,
 }
 
@@ -136,6 +492,38 @@ pub struct ImportModule<'a> {
     pub name: &'a str,
     pub variables: Vec<ImportVariable<'a>>,
 }
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeError<'a> {
+    pub left: &'a str,
+    pub right: &'a str,
+}
+
+impl<'a> ImportModule<'a> {
+    // The variables of `a` come first; one of `b` with a name already imported is dropped,
+    // whatever its alias.
+    pub fn merge_variables(
+        a: ImportModule<'a>,
+        b: ImportModule<'a>,
+    ) -> Result<ImportModule<'a>, MergeError<'a>> {
+        if strip_quotes(a.name) != strip_quotes(b.name) {
+            return Err(MergeError {
+                left: a.name,
+                right: b.name,
+            });
+        }
+        let mut variables = a.variables;
+        for v in b.variables {
+            if !variables.iter().any(|e| e.name.value == v.name.value) {
+                variables.push(v)
+            }
+        }
+        Ok(ImportModule {
+            name: a.name,
+            variables,
+        })
+    }
+}
 #[derive(Debug, Clone, PartialEq)]
 pub struct Function<'a> {
     pub name: Id<'a>,
@@ -143,6 +531,34 @@ pub struct Function<'a> {
     pub block: Option<Block<'a>>,
 }
 
+impl<'a> Function<'a> {
+    pub fn param_names<'s>(&'s self) -> impl Iterator<Item = &'a str> + 's {
+        self.params.names()
+    }
+
+    pub fn always_returns(&self) -> bool {
+        self.block.as_ref().is_some_and(Block::returns_value)
+    }
+
+    pub fn to_block(self) -> Block<'a> {
+        Block {
+            params: self.params,
+            statements: self.block.map(|b| b.statements).unwrap_or_default(),
+        }
+    }
+
+    pub fn to_closure(self) -> Block<'a> {
+        self.to_block()
+    }
+
+    // `this` is a keyword, so the result can be printed but not parsed back as Wren.
+    pub fn to_closure_with_receiver(self) -> Block<'a> {
+        let mut block = self.to_block();
+        block.params.ids.insert(0, Id { value: "this" });
+        block
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum RangeExpression<'a> {
     Call(Call<'a>),
@@ -155,6 +571,55 @@ pub struct Range<'a> {
     pub is_out: bool,
 }
 
+// Wren ranges may count down (`3..1`) and `...` leaves out the right endpoint,
+// so the queries only answer when both endpoints are number literals.
+impl<'a> Range<'a> {
+    pub fn contains_num(&self, n: i64) -> Option<bool> {
+        let ((lo, lo_closed), (hi, hi_closed)) = self.interval()?;
+        let n = n as f64;
+        Some((lo < n || (lo_closed && lo == n)) && (n < hi || (hi_closed && n == hi)))
+    }
+
+    pub fn is_empty_range(&self) -> Option<bool> {
+        self.bounds().map(|(left, right)| self.is_out && left == right)
+    }
+
+    pub fn overlaps(&self, other: &Range<'a>) -> Option<bool> {
+        let (a_lo, a_hi) = self.interval()?;
+        let (b_lo, b_hi) = other.interval()?;
+        let lo = match a_lo.0.partial_cmp(&b_lo.0)? {
+            Ordering::Less => b_lo,
+            Ordering::Greater => a_lo,
+            Ordering::Equal => (a_lo.0, a_lo.1 && b_lo.1),
+        };
+        let hi = match a_hi.0.partial_cmp(&b_hi.0)? {
+            Ordering::Less => a_hi,
+            Ordering::Greater => b_hi,
+            Ordering::Equal => (a_hi.0, a_hi.1 && b_hi.1),
+        };
+        Some(lo.0 < hi.0 || (lo.0 == hi.0 && lo.1 && hi.1))
+    }
+
+    fn bounds(&self) -> Option<(f64, f64)> {
+        match (&self.left, &self.right) {
+            (RangeExpression::Num(l), RangeExpression::Num(r)) => Some((l.as_f64(), r.as_f64())),
+            _ => None,
+        }
+    }
+
+    // The covered interval as (low, high), each with a flag telling whether it is included.
+    fn interval(&self) -> Option<((f64, bool), (f64, bool))> {
+        let (left, right) = self.bounds()?;
+        let left_end = (left, true);
+        let right_end = (right, !self.is_out);
+        if left <= right {
+            Some((left_end, right_end))
+        } else {
+            Some((right_end, left_end))
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum LogicOp {
     Gt,
@@ -189,6 +654,29 @@ pub enum Arithmetic<'a> {
     Bit(BitSign, Box<Arithmetic<'a>>),
 }
 
+impl<'a> Arithmetic<'a> {
+    // The operator and its right operand, e.g. `* 2` or `+ x - 1`.
+    pub fn to_wren_source(&self) -> String {
+        printer::arithmetic(self, 0)
+    }
+
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Arithmetic::Bit(BitSign::Or, _) => 4,
+            Arithmetic::Bit(BitSign::Xor, _) => 5,
+            Arithmetic::Bit(BitSign::And, _) => 6,
+            Arithmetic::Shift(..) => 7,
+            Arithmetic::Range(..) => 8,
+            Arithmetic::Add(..) => 12,
+            Arithmetic::Mul(..) => 13,
+            Arithmetic::Expression(_) => 15,
+        }
+    }
+    pub fn associates_left(&self) -> bool {
+        true
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum MulSign {
     Mul,
@@ -213,6 +701,28 @@ pub enum ClassStatement<'a> {
     Constructor(Id<'a>, Params<'a>, Block<'a>),
 }
 
+impl<'a> ClassStatement<'a> {
+    pub fn signature(&self) -> String {
+        let args = |n: usize| vec!["_"; n].join(",");
+        match self {
+            ClassStatement::Fn(f) => format!("{}({})", f.name.value, args(f.params.ids.len())),
+            ClassStatement::OpGetter(label, _) => match label {
+                GetterLabel::Id(id) => id.value.to_string(),
+                GetterLabel::Sub => "-".to_string(),
+                GetterLabel::Tilde => "~".to_string(),
+                GetterLabel::Bang => "!".to_string(),
+            },
+            ClassStatement::Setter(id, _, _) => format!("{}=(_)", id.value),
+            ClassStatement::OpSetter(label, _, _) => format!("{}(_)", label.operator()),
+            ClassStatement::SubscriptGet(e, _) => format!("[{}]", args(e.values.len())),
+            ClassStatement::SubscriptSet(e, _, _) => format!("[{}]=(_)", args(e.values.len())),
+            ClassStatement::Constructor(id, params, _) => {
+                format!("{}({})", id.value, args(params.ids.len()))
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum GetterLabel<'a> {
     Id(Id<'a>),
@@ -242,11 +752,100 @@ pub enum SetterLabel {
     NotEq,
     Is,
 }
+
+impl SetterLabel {
+    pub fn operator(&self) -> &'static str {
+        match self {
+            SetterLabel::Sub => "-",
+            SetterLabel::Mul => "*",
+            SetterLabel::Div => "/",
+            SetterLabel::Mod => "%",
+            SetterLabel::Add => "+",
+            SetterLabel::EllipsisIn => "..",
+            SetterLabel::EllipsisOut => "...",
+            SetterLabel::LShift => "<<",
+            SetterLabel::RShift => ">>",
+            SetterLabel::BitAnd => "&",
+            SetterLabel::BitOr => "|",
+            SetterLabel::BitXor => "^",
+            SetterLabel::Gt => ">",
+            SetterLabel::Lt => "<",
+            SetterLabel::Eq => "==",
+            SetterLabel::Le => "<=",
+            SetterLabel::Ge => ">=",
+            SetterLabel::NotEq => "!=",
+            SetterLabel::Is => "is",
+        }
+    }
+}
 #[derive(Debug, Clone, PartialEq)]
 pub enum Attribute<'a> {
     Simple(bool, AttributeValue<'a>),
     Group(bool, Id<'a>, Vec<AttributeValue<'a>>),
 }
+
+impl<'a> Attribute<'a> {
+    pub fn name(&self) -> &Id<'a> {
+        match self {
+            Attribute::Simple(_, v) => &v.id,
+            Attribute::Group(_, id, _) => id,
+        }
+    }
+    pub fn is_negated(&self) -> bool {
+        match self {
+            Attribute::Simple(neg, _) | Attribute::Group(neg, _, _) => *neg,
+        }
+    }
+    pub fn values(&self) -> &[AttributeValue<'a>] {
+        match self {
+            Attribute::Simple(_, v) => std::slice::from_ref(v),
+            Attribute::Group(_, _, vs) => vs,
+        }
+    }
+    // `#name`, `#!name = value` or `#name(key = value, other)`.
+    pub fn to_annotation_string(&self) -> String {
+        printer::attribute(self, 0)
+    }
+}
+
+pub fn find_attribute_by_name<'s, 'a>(
+    attrs: &'s [Attribute<'a>],
+    name: &str,
+) -> Option<&'s Attribute<'a>> {
+    attrs.iter().find(|a| a.name().value == name)
+}
+
+pub trait AttributeList<'a> {
+    fn find(&self, name: &str) -> Option<&Attribute<'a>>;
+    fn all_named<'s>(&'s self, name: &str) -> impl Iterator<Item = &'s Attribute<'a>>
+    where
+        'a: 's;
+}
+
+impl<'a> AttributeList<'a> for [Attribute<'a>] {
+    fn find(&self, name: &str) -> Option<&Attribute<'a>> {
+        find_attribute_by_name(self, name)
+    }
+    fn all_named<'s>(&'s self, name: &str) -> impl Iterator<Item = &'s Attribute<'a>>
+    where
+        'a: 's,
+    {
+        self.iter().filter(move |a| a.name().value == name)
+    }
+}
+
+impl<'a> AttributeList<'a> for Vec<Attribute<'a>> {
+    fn find(&self, name: &str) -> Option<&Attribute<'a>> {
+        self.as_slice().find(name)
+    }
+    fn all_named<'s>(&'s self, name: &str) -> impl Iterator<Item = &'s Attribute<'a>>
+    where
+        'a: 's,
+    {
+        self.as_slice().all_named(name)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct AttributeValue<'a> {
     pub id: Id<'a>,
@@ -266,6 +865,12 @@ impl Default for ClassBodyType{
     }
 }
 
+impl ClassBodyType {
+    pub fn is_static(&self) -> bool {
+        matches!(self, ClassBodyType::Static | ClassBodyType::ForeignStatic)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ClassUnit<'a> {
     pub attributes: Vec<Attribute<'a>>,
@@ -273,6 +878,18 @@ pub struct ClassUnit<'a> {
     pub statement: ClassStatement<'a>,
 }
 
+impl<'a> ClassUnit<'a> {
+    pub fn is_abstract(&self) -> bool {
+        matches!(
+            (&self.tpe, &self.statement),
+            (
+                ClassBodyType::Foreign | ClassBodyType::ForeignStatic,
+                ClassStatement::Fn(Function { block: None, .. })
+            )
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ClassDefinition<'a> {
     pub attributes: Vec<Attribute<'a>>,
@@ -282,6 +899,79 @@ pub struct ClassDefinition<'a> {
     pub elems: Vec<ClassUnit<'a>>,
 }
 
+impl<'a> ClassDefinition<'a> {
+    pub fn abstract_methods<'s>(&'s self) -> impl Iterator<Item = &'s Function<'a>> {
+        self.elems
+            .iter()
+            .filter(|u| u.is_abstract())
+            .filter_map(|u| match &u.statement {
+                ClassStatement::Fn(f) => Some(f),
+                _ => None,
+            })
+    }
+
+    pub fn all_method_signatures<'s>(&'s self) -> impl Iterator<Item = (ClassBodyType, String)> + 's {
+        self.elems
+            .iter()
+            .map(|u| (u.tpe.clone(), u.statement.signature()))
+    }
+
+    // Own signatures first, then the ones inherited through the classes of `script`.
+    // Constructors and static methods aren't inherited and an override hides the parent method.
+    pub fn all_method_signatures_in(&self, script: &Script<'a>) -> Vec<(ClassBodyType, String)> {
+        let mut res: Vec<_> = self.all_method_signatures().collect();
+        let mut visited = vec![self.name.value];
+        let mut parent = self.inherit.and_then(|p| script.get_class(p.value));
+        while let Some(cls) = parent {
+            if visited.contains(&cls.name.value) {
+                break;
+            }
+            visited.push(cls.name.value);
+            for u in &cls.elems {
+                if u.tpe.is_static() || matches!(u.statement, ClassStatement::Constructor(..)) {
+                    continue;
+                }
+                let signature = u.statement.signature();
+                if !res.iter().any(|(t, s)| !t.is_static() && *s == signature) {
+                    res.push((u.tpe.clone(), signature));
+                }
+            }
+            parent = cls.inherit.and_then(|p| script.get_class(p.value));
+        }
+        res
+    }
+
+    // A heuristic only: a foreign class without instance (`_x`) or static (`__x`) fields
+    // whose methods are all static or foreign, like `Num` or `Bool`.
+    // Constructors aren't counted as methods.
+    pub fn is_likely_value_type(&self) -> bool {
+        self.foreign
+            && !self.any_node(|n| matches!(n, Node::Call(c) if !c.id.is_public()))
+            && self
+                .elems
+                .iter()
+                .filter(|u| !matches!(u.statement, ClassStatement::Constructor(..)))
+                .all(|u| u.tpe != ClassBodyType::None)
+    }
+
+    // An assignment to an instance (`_x`) or static (`__x`) field in any body.
+    pub fn has_mutable_state(&self) -> bool {
+        self.any_node(|n| match n {
+            Node::Assignment(a) => a.modified_variable().is_some_and(|id| !id.is_public()),
+            _ => false,
+        })
+    }
+
+    fn any_node<P: Fn(Node) -> bool>(&self, pred: P) -> bool {
+        let mut found = false;
+        walk(Node::Class(self), |n| {
+            found = found || pred(n);
+            !found
+        });
+        found
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum AssignOp {
     Assign,
@@ -298,6 +988,48 @@ pub enum AssignOp {
     URShift,
 }
 
+impl AssignOp {
+    pub fn is_bitwise(&self) -> bool {
+        matches!(
+            self,
+            AssignOp::And
+                | AssignOp::Or
+                | AssignOp::Xor
+                | AssignOp::LShift
+                | AssignOp::RShift
+                | AssignOp::URShift
+        )
+    }
+
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(
+            self,
+            AssignOp::Add | AssignOp::Sub | AssignOp::Mul | AssignOp::Div | AssignOp::Mod
+        )
+    }
+
+    pub fn is_simple(&self) -> bool {
+        *self == AssignOp::Assign
+    }
+
+    pub fn to_symbol(&self) -> &'static str {
+        match self {
+            AssignOp::Assign => "=",
+            AssignOp::Add => "+=",
+            AssignOp::Sub => "-=",
+            AssignOp::Mul => "*=",
+            AssignOp::Div => "/=",
+            AssignOp::And => "&=",
+            AssignOp::Or => "|=",
+            AssignOp::Xor => "^=",
+            AssignOp::Mod => "%=",
+            AssignOp::LShift => "<<=",
+            AssignOp::RShift => ">>=",
+            AssignOp::URShift => ">>>=",
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Assignment<'a> {
     pub var: bool,
@@ -305,6 +1037,38 @@ pub struct Assignment<'a> {
     pub lhs: Expression<'a>,
     pub rhs: Box<Rhs<'a>>,
 }
+
+impl<'a> Assignment<'a> {
+    pub fn is_self_modifying(&self) -> bool {
+        !self.op.is_simple()
+    }
+
+    pub fn is_increment(&self) -> bool {
+        self.op == AssignOp::Add && self.rhs_is_one()
+    }
+
+    pub fn is_decrement(&self) -> bool {
+        self.op == AssignOp::Sub && self.rhs_is_one()
+    }
+
+    pub fn modified_variable(&self) -> Option<&Id<'a>> {
+        match &self.lhs {
+            Expression::Atom(AtomExpression::Call(Call {
+                id,
+                tail: None,
+                middle: BlockOrEnum::None,
+            })) => Some(id),
+            _ => None,
+        }
+    }
+
+    fn rhs_is_one(&self) -> bool {
+        matches!(
+            self.rhs.as_ref(),
+            Rhs::Expression(Expression::Atom(AtomExpression::Number(Number::Int(1))))
+        )
+    }
+}
 #[derive(Debug, Clone, PartialEq)]
 pub struct AssignmentNull<'a> {
     pub id: Id<'a>,
@@ -317,33 +1081,176 @@ pub enum Rhs<'a> {
     Assignments(Vec<Assignment<'a>>),
 }
 
+impl<'a> Rhs<'a> {
+    pub fn is_chained(&self) -> bool {
+        !matches!(self, Rhs::Expression(_))
+    }
+
+    pub fn as_expression<'s>(&'s self) -> Option<&'s Expression<'a>> {
+        match self {
+            Rhs::Expression(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    // The rhs is handed back as is on failure so the caller can still use it.
+    #[allow(clippy::result_large_err)]
+    pub fn into_expression(self) -> Result<Expression<'a>, Rhs<'a>> {
+        match self {
+            Rhs::Expression(e) => Ok(e),
+            other => Err(other),
+        }
+    }
+
+    // Only a single chained assignment, `Assignments` holds several.
+    pub fn as_assignment<'s>(&'s self) -> Option<&'s Assignment<'a>> {
+        match self {
+            Rhs::Assignment(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.flatten_chain().len()
+    }
+
+    pub fn flatten_chain(&self) -> Vec<&Assignment<'a>> {
+        let assignments = match self {
+            Rhs::Expression(_) => return vec![],
+            Rhs::Assignment(a) => std::slice::from_ref(a),
+            Rhs::Assignments(v) => v.as_slice(),
+        };
+        assignments
+            .iter()
+            .flat_map(|a| std::iter::once(a).chain(a.rhs.flatten_chain()))
+            .collect()
+    }
+}
+
+// The body of `if`, `while` and `for`, keeping apart `if (c) { a }` and `if (c) a`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockOrStatement<'a> {
+    Block(Block<'a>),
+    Single(Box<Statement<'a>>),
+}
+
+impl<'a> BlockOrStatement<'a> {
+    pub fn returns_value(&self) -> bool {
+        match self {
+            BlockOrStatement::Block(b) => b.returns_value(),
+            BlockOrStatement::Single(s) => s.returns_value(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct IfBranch<'a> {
     pub cond: Expression<'a>,
-    pub action: Statement<'a>,
+    pub action: BlockOrStatement<'a>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct If<'a> {
     pub main: IfBranch<'a>,
     pub others: Vec<IfBranch<'a>>,
-    pub els: Option<Statement<'a>>,
+    pub els: Option<BlockOrStatement<'a>>,
+}
+
+impl<'a> If<'a> {
+    // Variables tested by the `if` condition and every `else if` condition.
+    pub fn condition_variables(&self) -> Vec<&str> {
+        let mut vars = vec![];
+        for cond in self.branch_conditions() {
+            for v in cond.variables_referenced() {
+                if !vars.contains(&v) {
+                    vars.push(v)
+                }
+            }
+        }
+        vars
+    }
+
+    pub fn always_returns(&self) -> bool {
+        self.main.action.returns_value()
+            && self.others.iter().all(|b| b.action.returns_value())
+            && self.els.as_ref().is_some_and(BlockOrStatement::returns_value)
+    }
+
+    // The `else` counts as a branch, it just has no condition.
+    pub fn branch_count(&self) -> usize {
+        1 + self.others.len() + usize::from(self.has_else())
+    }
+
+    pub fn has_else(&self) -> bool {
+        self.els.is_some()
+    }
+
+    pub fn branch_conditions<'s>(&'s self) -> impl Iterator<Item = &'s Expression<'a>> {
+        std::iter::once(&self.main)
+            .chain(&self.others)
+            .map(|b| &b.cond)
+    }
 }
 #[derive(Debug, Clone, PartialEq)]
 pub enum WhileCond<'a> {
     Expression(Expression<'a>),
     Assignment(Assignment<'a>),
 }
+
+impl<'a> WhileCond<'a> {
+    pub fn as_expression<'s>(&'s self) -> Option<&'s Expression<'a>> {
+        match self {
+            WhileCond::Expression(e) => Some(e),
+            WhileCond::Assignment(_) => None,
+        }
+    }
+
+    pub fn as_assignment<'s>(&'s self) -> Option<&'s Assignment<'a>> {
+        match self {
+            WhileCond::Assignment(a) => Some(a),
+            WhileCond::Expression(_) => None,
+        }
+    }
+
+    // An assignment evaluates to the assigned value, so that is what gets tested.
+    pub fn condition_expression<'s>(&'s self) -> Option<&'s Expression<'a>> {
+        match self {
+            WhileCond::Expression(e) => Some(e),
+            WhileCond::Assignment(a) => match a.rhs.as_ref() {
+                Rhs::Expression(e) => Some(e),
+                _ => None,
+            },
+        }
+    }
+}
 #[derive(Debug, Clone, PartialEq)]
 pub struct While<'a> {
     pub cond: WhileCond<'a>,
-    pub body: Statement<'a>,
+    pub body: BlockOrStatement<'a>,
 }
 #[derive(Debug, Clone, PartialEq)]
 pub struct For<'a> {
     pub elem: Id<'a>,
     pub collection: Expression<'a>,
-    pub body: Statement<'a>,
+    pub body: BlockOrStatement<'a>,
+}
+
+impl<'a> For<'a> {
+    pub fn is_range_for(&self) -> bool {
+        matches!(self.collection, Expression::Atom(AtomExpression::Range(_)))
+    }
+    pub fn is_collection_for(&self) -> bool {
+        !self.is_range_for()
+    }
+    // The left and right end of the range and whether the right one is left out (`...`).
+    pub fn range_bounds(&self) -> Option<(RangeExpression<'a>, RangeExpression<'a>, bool)> {
+        match &self.collection {
+            Expression::Atom(AtomExpression::Range(r)) => {
+                Some((r.left.clone(), r.right.clone(), r.is_out))
+            }
+            _ => None,
+        }
+    }
 }
 #[derive(Debug, Clone, PartialEq)]
 pub enum Unit<'a> {
@@ -357,3 +1264,136 @@ pub enum Unit<'a> {
 pub struct Script<'a> {
     pub units: Vec<Unit<'a>>,
 }
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseUnit<'a> {
+    Script(Script<'a>),
+    Statement(Statement<'a>),
+    Expression(Expression<'a>),
+}
+
+impl<'a> Script<'a> {
+    pub fn get_class<'s>(&'s self, name: &str) -> Option<&'s ClassDefinition<'a>> {
+        self.units.iter().find_map(|u| match u {
+            Unit::Class(c) if c.name.value == name => Some(c),
+            _ => None,
+        })
+    }
+
+    pub fn functions<'s>(&'s self) -> impl Iterator<Item = &'s Function<'a>> {
+        self.units.iter().filter_map(|u| match u {
+            Unit::Fn(f) => Some(f),
+            _ => None,
+        })
+    }
+
+    pub fn get_function<'s>(&'s self, name: &str) -> Option<&'s Function<'a>> {
+        self.functions().find(|f| f.name.value == name)
+    }
+
+    pub fn imports<'s>(&'s self) -> impl Iterator<Item = &'s ImportModule<'a>> {
+        self.units.iter().filter_map(|u| match u {
+            Unit::Import(i) => Some(i),
+            _ => None,
+        })
+    }
+
+    pub fn top_level_statements<'s>(&'s self) -> impl Iterator<Item = &'s Statement<'a>> {
+        self.units.iter().filter_map(|u| match u {
+            Unit::Statement(s) => Some(s),
+            _ => None,
+        })
+    }
+
+    pub fn top_level_expressions<'s>(&'s self) -> impl Iterator<Item = &'s Expression<'a>> {
+        self.top_level_statements().filter_map(|s| match s {
+            Statement::Expression(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    pub fn rename_class(self, old_name: &str, new_name: &'a str) -> Script<'a> {
+        transform::rename_class(self, old_name, new_name)
+    }
+
+    pub fn rename_function(self, old_name: &str, new_name: &'a str) -> Script<'a> {
+        transform::rename_function(self, old_name, new_name)
+    }
+
+    pub fn reorder_units(self) -> Script<'a> {
+        transform::reorder_units(self)
+    }
+
+    pub fn merge(self, other: Script<'a>) -> Script<'a> {
+        transform::merge(self, other)
+    }
+
+    pub fn transform<F: FnMut(Unit<'a>) -> Unit<'a>>(self, f: F) -> Script<'a> {
+        transform::transform(self, f)
+    }
+
+    pub fn transform_expressions<F>(self, f: F) -> Script<'a>
+    where
+        F: FnMut(Expression<'a>) -> Expression<'a>,
+    {
+        transform::transform_expressions(self, f)
+    }
+
+    // One entry per unit, `source` being the text the script was parsed from.
+    pub fn extract_doc_comments(&self, source: &'a str) -> Vec<Option<&'a str>> {
+        analysis::extract_doc_comments(&self.units, source)
+    }
+
+    pub fn size(&self) -> analysis::ScriptMetrics {
+        analysis::metrics(self)
+    }
+
+    pub fn walk_calls<'s>(&'s self) -> impl Iterator<Item = &'s Call<'a>> {
+        analysis::walk_calls(self)
+    }
+
+    pub fn to_dot(&self) -> String {
+        analysis::to_dot(self)
+    }
+
+    pub fn is_library(&self) -> bool {
+        self.units
+            .iter()
+            .all(|u| matches!(u, Unit::Class(_) | Unit::Import(_)))
+    }
+}
+
+impl<'a> IntoIterator for Script<'a> {
+    type Item = Unit<'a>;
+    type IntoIter = std::vec::IntoIter<Unit<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.units.into_iter()
+    }
+}
+
+impl<'s, 'a> IntoIterator for &'s Script<'a> {
+    type Item = &'s Unit<'a>;
+    type IntoIter = std::slice::Iter<'s, Unit<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.units.iter()
+    }
+}
+
+impl<'s, 'a> IntoIterator for &'s mut Script<'a> {
+    type Item = &'s mut Unit<'a>;
+    type IntoIter = std::slice::IterMut<'s, Unit<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.units.iter_mut()
+    }
+}
+
+impl<'a> FromIterator<Unit<'a>> for Script<'a> {
+    fn from_iter<I: IntoIterator<Item = Unit<'a>>>(iter: I) -> Self {
+        Script {
+            units: iter.into_iter().collect(),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::parser::ast::{Script, Unit};
+use crate::parser::lexer::Token;
+use crate::parser::parser::CypherParser;
+use crate::parser::result::ParseResult::{Error, Fail, Success};
+use crate::parser::{ParseError, ParseErrorKind, SourceId};
+use std::path::{Path, PathBuf};
+
+pub struct DiagnosticCollector<'a> {
+    parser: CypherParser<'a>,
+}
+
+impl<'a> DiagnosticCollector<'a> {
+    pub fn new(parser: CypherParser<'a>) -> Self {
+        DiagnosticCollector { parser }
+    }
+
+    // Parses unit by unit. A unit that can't be parsed is reported once
+    // and the parser skips ahead to the next token that can start a unit.
+    pub fn script(&self) -> (Script<'a>, Vec<ParseError<'a>>) {
+        let mut units: Vec<Unit<'a>> = vec![];
+        let mut errors = vec![];
+        let mut pos = 0;
+
+        while self.parser.token(pos).is_ok() {
+            match self.parser.file_unit(pos) {
+                Success(unit, next) => {
+                    units.push(unit);
+                    pos = next;
+                }
+                Fail(p) => {
+                    errors.push(self.parser.error(ParseErrorKind::FailedOnUnit(p)));
+                    pos = self.recover(pos + 1);
+                }
+                Error(e) => {
+                    errors.push(self.parser.error(e));
+                    pos = self.recover(pos + 1);
+                }
+            }
+        }
+
+        (Script { units }, errors)
+    }
+
+    // Same as `script`, with every error located in the parsed source.
+    pub fn diagnostics(&self) -> (Script<'a>, Vec<ParseDiagnostic<'a>>) {
+        let (script, errors) = self.script();
+        let source = self.parser.source();
+        let diagnostics = errors
+            .into_iter()
+            .map(|e| ParseDiagnostic::new(e, source))
+            .collect();
+        (script, diagnostics)
+    }
+
+    fn recover(&self, pos: usize) -> usize {
+        let is_unit_start = |t: &Token| {
+            matches!(
+                t,
+                Token::Class
+                    | Token::Foreign
+                    | Token::Import
+                    | Token::Var
+                    | Token::If
+                    | Token::While
+                    | Token::For
+                    | Token::Return
+                    | Token::Hash
+            )
+        };
+        match self.parser.scan_forward(pos, is_unit_start) {
+            Success(idx, _) => idx,
+            Fail(p) | Error(ParseErrorKind::ReachedEOF(p)) => p,
+            Error(_) => pos,
+        }
+    }
+}
+
+// An error with its 1-indexed line and column, see `ParseError::location`.
+#[derive(Debug, Clone)]
+pub struct ParseDiagnostic<'a> {
+    pub error: ParseError<'a>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl<'a> ParseDiagnostic<'a> {
+    pub fn new(error: ParseError<'a>, source: &str) -> Self {
+        let (line, column) = error.location(source);
+        ParseDiagnostic {
+            error,
+            line,
+            column,
+        }
+    }
+}
+
+impl<'a> CypherParser<'a> {
+    pub fn collect_diagnostics(self) -> DiagnosticCollector<'a> {
+        DiagnosticCollector::new(self)
+    }
+}
+
+// Ids are handed out in the order the files are added.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    paths: Vec<PathBuf>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap::default()
+    }
+
+    pub fn add<P: Into<PathBuf>>(&mut self, path: P) -> SourceId {
+        self.paths.push(path.into());
+        SourceId(self.paths.len() as u32 - 1)
+    }
+
+    pub fn path(&self, id: SourceId) -> Option<&Path> {
+        self.paths.get(id.0 as usize).map(PathBuf::as_path)
+    }
+
+    // The file, line and column of an error, `source` being the text of that file.
+    pub fn locate(&self, error: &ParseError, source: &str) -> Option<(&Path, usize, usize)> {
+        let path = self.path(error.source_id?)?;
+        let (line, column) = error.location(source);
+        Some((path, line, column))
+    }
+}
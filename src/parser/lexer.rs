@@ -1,7 +1,7 @@
 use std::ops::Range;
 
 use crate::parser::ast::Number;
-use crate::parser::ParseError;
+use crate::parser::{ParseError, ParseErrorKind};
 use logos::Lexer;
 use logos::Logos;
 use std::error::Error;
@@ -11,35 +11,223 @@ use std::num::ParseIntError;
 pub struct CypherLexer<'a> {
     pub(crate) source: &'a str,
     pub(crate) tokens: Vec<Token<'a>>,
+    pub(crate) spans: Vec<Range<usize>>,
 }
 
 impl<'a> CypherLexer<'a> {
+    // In the same order as KEYWORDS.
+    pub fn keywords() -> &'static [Token<'static>] {
+        &KEYWORD_TOKENS
+    }
+    pub fn keyword_strings() -> &'static [&'static str] {
+        &KEYWORDS
+    }
+    pub fn operators() -> &'static [Token<'static>] {
+        &OPERATOR_TOKENS
+    }
     pub fn new(source: &'a str) -> Result<Self, ParseError> {
         let mut delegate = Token::lexer(source);
         let mut tokens = vec![];
+        let mut spans = vec![];
 
         while let Some(t) = delegate.next() {
             match t {
                 Token::Error => {
-                    return Err(ParseError::BadToken(delegate.slice(), delegate.span()));
+                    return Err(ParseErrorKind::BadToken(delegate.slice(), delegate.span()).into());
+                }
+                t => {
+                    tokens.push(t);
+                    spans.push(delegate.span());
                 }
-                t => tokens.push(t),
             }
         }
 
-        Ok(CypherLexer { source, tokens })
+        Ok(CypherLexer {
+            source,
+            tokens,
+            spans,
+        })
     }
-    pub fn token(&self, pos: usize) -> Result<(&Token<'a>, usize), ParseError<'a>> {
+    pub fn from_tokens(source: &'a str, tokens: Vec<Token<'a>>) -> CypherLexer<'a> {
+        CypherLexer {
+            source,
+            tokens,
+            spans: vec![],
+        }
+    }
+    pub fn token(&self, pos: usize) -> Result<(&Token<'a>, usize), ParseErrorKind<'a>> {
         match self.tokens.get(pos) {
-            None => Err(ParseError::ReachedEOF(pos)),
+            None => Err(ParseErrorKind::ReachedEOF(pos)),
             Some(t) => Ok((t, pos)),
         }
     }
+    // Re-lexes the edited region only: lexing restarts after the last token preceding the edit
+    // and stops as soon as it lines up with an old token behind the edit again.
+    // Tokens outside of that region are only moved, without lexing them again.
+    pub fn retokenize<'b>(
+        &self,
+        old_source_range: Range<usize>,
+        new_source: &'b str,
+    ) -> Result<CypherLexer<'b>, ParseError<'b>> {
+        if self.spans.len() != self.tokens.len() {
+            return CypherLexer::new(new_source);
+        }
+        let shift = new_source.len() as isize - self.source.len() as isize;
+        let shifted = |span: &Range<usize>| {
+            (span.start as isize + shift) as usize..(span.end as isize + shift) as usize
+        };
+
+        let prefix = self.spans.partition_point(|s| s.end < old_source_range.start);
+        let mut tokens = vec![];
+        let mut spans = vec![];
+        for (token, span) in self.tokens[..prefix].iter().zip(&self.spans) {
+            tokens.push(rebase(token, new_source, span.clone()));
+            spans.push(span.clone());
+        }
+
+        let lex_start = prefix.checked_sub(1).map_or(0, |i| self.spans[i].end);
+        let edit_end = (old_source_range.end as isize + shift) as usize;
+        let mut old = self.spans.partition_point(|s| s.start < old_source_range.end);
+        let mut delegate = Token::lexer(&new_source[lex_start..]);
+
+        while let Some(t) = delegate.next() {
+            let span = delegate.span().start + lex_start..delegate.span().end + lex_start;
+            if let Token::Error = t {
+                return Err(ParseErrorKind::BadToken(delegate.slice(), span).into());
+            }
+            if span.start >= edit_end {
+                while old < self.spans.len() && shifted(&self.spans[old]).start < span.start {
+                    old += 1;
+                }
+                let synced = self.spans.get(old).map(shifted).filter(|s| {
+                    *s == span && rebase(&self.tokens[old], new_source, s.clone()) == t
+                });
+                if synced.is_some() {
+                    for (token, span) in self.tokens[old..]
+                        .iter()
+                        .zip(self.spans[old..].iter().map(shifted))
+                    {
+                        tokens.push(rebase(token, new_source, span.clone()));
+                        spans.push(span);
+                    }
+                    break;
+                }
+            }
+            tokens.push(t);
+            spans.push(span);
+        }
+
+        Ok(CypherLexer {
+            source: new_source,
+            tokens,
+            spans,
+        })
+    }
     pub fn len(&self) -> usize {
         self.tokens.len()
     }
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+    pub fn count_occurrences<F: Fn(&Token<'a>) -> bool>(&self, predicate: F) -> usize {
+        self.tokens.iter().filter(|t| predicate(t)).count()
+    }
+    pub fn count_keyword_occurrences(&self, keyword: &Token<'a>) -> usize {
+        self.count_occurrences(|t| t == keyword)
+    }
+    pub fn token_at_byte_offset(&self, byte_offset: usize) -> Option<(&Token<'a>, usize)> {
+        let idx = self.spans.partition_point(|s| s.end <= byte_offset);
+        match self.spans.get(idx) {
+            Some(span) if span.contains(&byte_offset) => Some((&self.tokens[idx], idx)),
+            _ => None,
+        }
+    }
+    // Tokens along with their byte ranges, empty for a lexer built by `from_tokens`.
+    pub fn tokens<'s>(&'s self) -> impl Iterator<Item = (&'s Token<'a>, Range<usize>)> + 's {
+        self.tokens.iter().zip(self.spans.iter().cloned())
+    }
+    // Source text covered by the tokens in `token_start..token_end`.
+    pub fn source_slice(&self, token_start: usize, token_end: usize) -> Option<&'a str> {
+        if token_start >= token_end {
+            return None;
+        }
+        let start = self.spans.get(token_start)?.start;
+        let end = self.spans.get(token_end - 1)?.end;
+        self.source.get(start..end)
+    }
+}
+
+// Every keyword with its token, so that the two lists can't get out of step.
+macro_rules! keywords {
+    ($($word:literal => $variant:ident),* $(,)?) => {
+        pub const KEYWORDS: [&str; [$($word),*].len()] = [$($word),*];
+
+        const KEYWORD_TOKENS: [Token<'static>; KEYWORDS.len()] = [$(Token::$variant),*];
+    };
 }
 
+keywords!(
+    "as" => As,
+    "break" => Break,
+    "class" => Class,
+    "construct" => Construct,
+    "continue" => Continue,
+    "else" => Else,
+    "false" => False,
+    "true" => True,
+    "for" => For,
+    "foreign" => Foreign,
+    "if" => If,
+    "import" => Import,
+    "in" => In,
+    "is" => Is,
+    "null" => Null,
+    "return" => Return,
+    "static" => Static,
+    "var" => Var,
+    "while" => While,
+);
+
+const OPERATOR_TOKENS: [Token<'static>; 37] = [
+    Token::Equal,
+    Token::NotEqual,
+    Token::And,
+    Token::Or,
+    Token::Inc,
+    Token::Dec,
+    Token::Add,
+    Token::Sub,
+    Token::Mult,
+    Token::Div,
+    Token::Mod,
+    Token::BitAnd,
+    Token::BitOr,
+    Token::Bang,
+    Token::Question,
+    Token::Gt,
+    Token::Ge,
+    Token::Lt,
+    Token::Le,
+    Token::Tilde,
+    Token::Caret,
+    Token::Assign,
+    Token::AddAssign,
+    Token::SubAssign,
+    Token::MultAssign,
+    Token::AndAssign,
+    Token::OrAssign,
+    Token::XOrAssign,
+    Token::ModAssign,
+    Token::DivAssign,
+    Token::EllipsisIn,
+    Token::EllipsisOut,
+    Token::RShift,
+    Token::LShift,
+    Token::RShiftAssign,
+    Token::LShiftAssign,
+    Token::URShiftAssign,
+];
+
 #[derive(Logos, Debug, Copy, Clone, PartialEq)]
 #[logos(subpattern digit = r"[0-9]([0-9_]*[0-9])?")]
 #[logos(subpattern letter = r"[a-zA-Z_]")]
@@ -209,6 +397,263 @@ pub enum Token<'a> {
     Error,
 }
 
+// Every token without a payload, so that the kind and the token can be mapped both ways.
+macro_rules! payload_free_tokens {
+    ($($variant:ident),* $(,)?) => {
+        impl<'a> Token<'a> {
+            pub fn kind(&self) -> TokenKind {
+                match self {
+                    Token::Id(_) => TokenKind::Id,
+                    Token::StringLit(_) => TokenKind::StringLit,
+                    Token::CharLit(_) => TokenKind::CharLit,
+                    Token::TextBlock(_) => TokenKind::TextBlock,
+                    Token::Digit(_) => TokenKind::Digit,
+                    $(Token::$variant => TokenKind::$variant,)*
+                }
+            }
+        }
+
+        impl TokenKind {
+            // `None` for the kinds whose tokens carry a payload.
+            pub fn token(&self) -> Option<Token<'static>> {
+                match self {
+                    $(TokenKind::$variant => Some(Token::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+payload_free_tokens!(
+    As,
+    Break,
+    Class,
+    Construct,
+    Continue,
+    Else,
+    False,
+    True,
+    For,
+    Foreign,
+    If,
+    Import,
+    In,
+    Is,
+    Null,
+    Return,
+    Static,
+    Var,
+    While,
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBrack,
+    RBrack,
+    Colon,
+    Semi,
+    Comma,
+    Dot,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+    Inc,
+    Dec,
+    Add,
+    Sub,
+    Mult,
+    Div,
+    BitAnd,
+    BitOr,
+    Bang,
+    Question,
+    Hash,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Tilde,
+    Caret,
+    Assign,
+    AddAssign,
+    SubAssign,
+    MultAssign,
+    AndAssign,
+    OrAssign,
+    XOrAssign,
+    ModAssign,
+    DivAssign,
+    Mod,
+    EllipsisIn,
+    EllipsisOut,
+    RShift,
+    LShift,
+    RShiftAssign,
+    LShiftAssign,
+    URShiftAssign,
+    Comment,
+    Whitespace,
+    Error,
+);
+
+impl<'a> Token<'a> {
+    pub fn is_keyword(&self) -> bool {
+        self.kind().is_keyword()
+    }
+
+    pub fn is_operator(&self) -> bool {
+        self.kind().is_operator()
+    }
+
+    pub fn is_literal(&self) -> bool {
+        self.kind().is_literal()
+    }
+
+    pub fn is_punctuation(&self) -> bool {
+        self.kind().is_punctuation()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Id,
+
+    StringLit,
+    CharLit,
+    TextBlock,
+
+    Digit,
+
+    As,
+    Break,
+    Class,
+    Construct,
+    Continue,
+    Else,
+    False,
+    True,
+    For,
+    Foreign,
+    If,
+    Import,
+    In,
+    Is,
+    Null,
+    Return,
+    Static,
+    Var,
+    While,
+
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBrack,
+    RBrack,
+    Colon,
+    Semi,
+    Comma,
+    Dot,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+    Inc,
+    Dec,
+    Add,
+    Sub,
+    Mult,
+    Div,
+    BitAnd,
+    BitOr,
+    Bang,
+    Question,
+    Hash,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Tilde,
+    Caret,
+    Assign,
+    AddAssign,
+    SubAssign,
+    MultAssign,
+    AndAssign,
+    OrAssign,
+    XOrAssign,
+    ModAssign,
+    DivAssign,
+    Mod,
+    EllipsisIn,
+    EllipsisOut,
+    RShift,
+    LShift,
+    RShiftAssign,
+    LShiftAssign,
+    URShiftAssign,
+
+    Comment,
+
+    Whitespace,
+
+    Error,
+}
+
+impl TokenKind {
+    pub fn is_keyword(&self) -> bool {
+        self.token().is_some_and(|t| KEYWORD_TOKENS.contains(&t))
+    }
+
+    pub fn is_operator(&self) -> bool {
+        self.token().is_some_and(|t| OPERATOR_TOKENS.contains(&t))
+    }
+
+    pub fn is_literal(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::Digit
+                | TokenKind::StringLit
+                | TokenKind::CharLit
+                | TokenKind::TextBlock
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::Null
+        )
+    }
+
+    pub fn is_punctuation(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::LParen
+                | TokenKind::RParen
+                | TokenKind::LBrace
+                | TokenKind::RBrace
+                | TokenKind::LBrack
+                | TokenKind::RBrack
+                | TokenKind::Colon
+                | TokenKind::Semi
+                | TokenKind::Comma
+                | TokenKind::Dot
+                | TokenKind::Hash
+        )
+    }
+}
+
+// The same token at `span` of `source`: only the payloads borrowing from the source are sliced again.
+fn rebase<'b>(token: &Token<'_>, source: &'b str, span: Range<usize>) -> Token<'b> {
+    match token {
+        Token::Id(_) => Token::Id(&source[span]),
+        Token::StringLit(_) => Token::StringLit(&source[span]),
+        Token::CharLit(_) => Token::CharLit(&source[span]),
+        Token::TextBlock(_) => Token::TextBlock(&source[span]),
+        Token::Digit(n) => Token::Digit(*n),
+        t => t.kind().token().unwrap_or(Token::Error),
+    }
+}
+
 fn number<'a>(lex: &mut Lexer<'a, Token<'a>>) -> Result<Number, String> {
     lex.slice()
         .parse::<i64>()
@@ -1,20 +1,211 @@
+use crate::parser::ast::{ClassDefinition, Expression, Function, ParseUnit, Script};
+use crate::parser::result::ParseResult;
+use crate::parser::lexer::CypherLexer;
+use crate::parser::parser::CypherParser;
+use crate::parser::util::line_column;
 use std::ops::Range;
 #[macro_use]
-mod parser;
-mod ast;
-mod lexer;
-mod result;
+pub(crate) mod parser;
+pub(crate) mod analysis;
+pub(crate) mod diagnostic;
+pub(crate) mod ast;
+pub(crate) mod lexer;
+mod printer;
+pub(crate) mod result;
+mod transform;
+mod util;
+pub(crate) mod visitor;
 
 #[cfg(test)]
 mod tests;
 
 #[derive(Debug, Clone)]
-pub enum ParseError<'a> {
+pub enum ParseErrorKind<'a> {
     BadToken(&'a str, Range<usize>),
     FailedOnValidation(&'a str, usize),
-    FinishedOnFail,
+    FailedOnUnit(usize),
+    FinishedOnFail(usize),
     ReachedEOF(usize),
     UnreachedEOF(usize),
+    Contextualised {
+        context: &'a str,
+        inner: Box<ParseErrorKind<'a>>,
+    },
+}
+
+// `source_id` is set by parsers created with `CypherParser::with_source_id`.
+#[derive(Debug, Clone)]
+pub struct ParseError<'a> {
+    pub kind: ParseErrorKind<'a>,
+    pub source_id: Option<SourceId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SourceId(pub u32);
+
+impl<'a> From<ParseErrorKind<'a>> for ParseError<'a> {
+    fn from(kind: ParseErrorKind<'a>) -> Self {
+        ParseError {
+            kind,
+            source_id: None,
+        }
+    }
+}
+
+impl<'a> ParseError<'a> {
+    pub fn location(&self, source: &str) -> (usize, usize) {
+        self.kind.location(source)
+    }
+
+    pub fn to_string_with_context(&self, source: &str, context_lines: usize) -> String {
+        self.kind.to_string_with_context(source, context_lines)
+    }
+}
+
+impl<'a> ParseErrorKind<'a> {
+    // Token positions are mapped back to the source by lexing it again.
+    pub fn location(&self, source: &str) -> (usize, usize) {
+        let token_offset = |pos: usize| {
+            CypherLexer::new(source)
+                .ok()
+                .and_then(|l| l.spans.get(pos).map(|s| s.start))
+                .unwrap_or(source.len())
+        };
+        let offset = match self {
+            ParseErrorKind::BadToken(_, range) => range.start,
+            ParseErrorKind::FailedOnValidation(_, pos)
+            | ParseErrorKind::FailedOnUnit(pos)
+            | ParseErrorKind::FinishedOnFail(pos)
+            | ParseErrorKind::ReachedEOF(pos)
+            | ParseErrorKind::UnreachedEOF(pos) => token_offset(*pos),
+            ParseErrorKind::Contextualised { inner, .. } => return inner.location(source),
+        };
+        line_column(source, offset)
+    }
+
+    // Formatted after rustc: the message, the location and the offending line marked with `^`,
+    // framed by up to `context_lines` lines on either side.
+    pub fn to_string_with_context(&self, source: &str, context_lines: usize) -> String {
+        let (line, column) = self.location(source);
+        let lines: Vec<&str> = source.lines().collect();
+        let first = line.saturating_sub(context_lines).max(1);
+        let last = (line + context_lines).min(lines.len()).max(line);
+        let width = last.to_string().len();
+        let pad = " ".repeat(width);
+
+        let mut res = format!(
+            "error: {}\n{}--> {}:{}\n{} |\n",
+            self.message(),
+            pad,
+            line,
+            column,
+            pad
+        );
+        for n in first..=last {
+            match lines.get(n - 1).filter(|l| !l.is_empty()) {
+                Some(text) => res.push_str(&format!("{:>width$} | {}\n", n, text, width = width)),
+                None => res.push_str(&format!("{:>width$} |\n", n, width = width)),
+            }
+            if n == line {
+                let marker = match self.inner() {
+                    ParseErrorKind::BadToken(token, _) => token.chars().count().max(1),
+                    _ => 1,
+                };
+                let marker = " ".repeat(column - 1) + &"^".repeat(marker);
+                res.push_str(&format!("{} | {}\n", pad, marker));
+            }
+        }
+        res
+    }
+
+    // The token the error was found at, `None` for a bad token that never made it to the parser.
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            ParseErrorKind::BadToken(..) => None,
+            ParseErrorKind::FailedOnValidation(_, pos)
+            | ParseErrorKind::FailedOnUnit(pos)
+            | ParseErrorKind::FinishedOnFail(pos)
+            | ParseErrorKind::ReachedEOF(pos)
+            | ParseErrorKind::UnreachedEOF(pos) => Some(*pos),
+            ParseErrorKind::Contextualised { inner, .. } => inner.position(),
+        }
+    }
+
+    fn inner(&self) -> &ParseErrorKind<'a> {
+        match self {
+            ParseErrorKind::Contextualised { inner, .. } => inner.inner(),
+            e => e,
+        }
+    }
+
+    pub(crate) fn message(&self) -> String {
+        match self {
+            ParseErrorKind::BadToken(token, _) => format!("unexpected token `{}`", token),
+            ParseErrorKind::FailedOnValidation(msg, _) => msg.to_string(),
+            ParseErrorKind::FailedOnUnit(pos) => format!("failed to parse unit at position {}", pos),
+            ParseErrorKind::FinishedOnFail(_) => "the input doesn't match".to_string(),
+            ParseErrorKind::ReachedEOF(_) => "unexpected end of input".to_string(),
+            ParseErrorKind::UnreachedEOF(_) => "expected end of input".to_string(),
+            ParseErrorKind::Contextualised { context, inner } => {
+                format!("{} in {}", inner.message(), context)
+            }
+        }
+    }
+}
+
+pub fn parse_script(src: &str) -> Result<Script<'_>, ParseError<'_>> {
+    let parser = CypherParser::new(src)?;
+    parser.parse(|pos| parser.script(pos))
+}
+
+pub fn parse_expression_list(src: &str) -> Result<Vec<Expression<'_>>, ParseError<'_>> {
+    let parser = CypherParser::new(src)?;
+    parser.parse(|pos| parser.expression_list(pos))
+}
+
+pub fn parse_non_empty_expression_list(
+    src: &str,
+) -> Result<Vec<Expression<'_>>, ParseError<'_>> {
+    let parser = CypherParser::new(src)?;
+    parser.parse(|pos| parser.enumeration(pos).map(|e| e.values))
+}
+
+pub fn parse_functions(src: &str) -> Result<Vec<Function<'_>>, ParseError<'_>> {
+    let parser = CypherParser::new(src)?;
+    parser.parse(|pos| parser.function_list(pos))
+}
+
+pub fn parse_classes(src: &str) -> Result<Vec<ClassDefinition<'_>>, ParseError<'_>> {
+    let parser = CypherParser::new(src)?;
+    parser.parse(|pos| parser.class_def_list(pos))
+}
+
+// Tries an expression, a statement and a script in that order, so the narrowest unit
+// covering the whole input is returned and a script only when it takes more than one unit.
+// When none of them covers the whole input, the error of the attempt that got furthest is returned,
+// preferring the broader unit on a tie.
+pub fn parse_to_completion<'a>(src: &'a str) -> Result<ParseUnit<'a>, ParseError<'a>> {
+    let parser = CypherParser::new(src)?;
+    let script = || parser.script(0).map(ParseUnit::Script);
+    let statement = || parser.statement(0).map(ParseUnit::Statement);
+    let expression = || parser.expression(0).map(ParseUnit::Expression);
+    let attempts: [&dyn Fn() -> ParseResult<'a, ParseUnit<'a>>; 3] =
+        [&expression, &statement, &script];
+
+    let mut furthest: Option<(usize, ParseResult<'a, ParseUnit<'a>>)> = None;
+    for attempt in attempts {
+        let res = parser.validate_eof(attempt());
+        let reached = match &res {
+            ParseResult::Success(..) => return parser.finish(res).map(|(u, _)| u),
+            ParseResult::Fail(pos) => *pos,
+            ParseResult::Error(e) => e.position().unwrap_or(0),
+        };
+        if furthest.as_ref().is_none_or(|(p, _)| reached >= *p) {
+            furthest = Some((reached, res));
+        }
+    }
+    let res = furthest.map_or(ParseResult::Fail(0), |(_, res)| res);
+    parser.finish(res).map(|(u, _)| u)
 }
 
 #[macro_export]
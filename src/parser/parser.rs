@@ -1,36 +1,85 @@
 use crate::parser::ast::*;
 use crate::parser::lexer::Token::Class;
-use crate::parser::lexer::{CypherLexer, Token};
+use crate::parser::lexer::{CypherLexer, Token, KEYWORDS};
 use crate::parser::result::ParseResult;
+use crate::parser::util::strip_quotes;
 use crate::parser::result::ParseResult::{Error, Fail, Success};
-use crate::parser::ParseError;
-use crate::parser::ParseError::{ReachedEOF, UnreachedEOF};
+use crate::parser::ParseErrorKind::{FailedOnValidation, FinishedOnFail, ReachedEOF, UnreachedEOF};
+use crate::parser::{ParseError, ParseErrorKind, SourceId};
 use crate::token;
 use std::borrow::Borrow;
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::iter::Map;
 
 pub struct CypherParser<'a> {
     lexer: CypherLexer<'a>,
+    max_expression_depth: Option<usize>,
+    // How many expressions are being parsed one inside the other.
+    expression_depth: Cell<usize>,
+    source_id: Option<SourceId>,
 }
 
 impl<'a> CypherParser<'a> {
     pub fn new(src: &'a str) -> Result<Self, ParseError> {
+        Ok(CypherParser::from_lexer(CypherLexer::new(src)?))
+    }
+    pub fn from_lexer(lexer: CypherLexer<'a>) -> Self {
+        CypherParser {
+            lexer,
+            max_expression_depth: None,
+            expression_depth: Cell::new(0),
+            source_id: None,
+        }
+    }
+    pub fn with_source_id(src: &'a str, source_id: SourceId) -> Result<Self, ParseError<'a>> {
+        let lexer = CypherLexer::new(src).map_err(|e| ParseError {
+            source_id: Some(source_id),
+            ..e
+        })?;
         Ok(CypherParser {
-            lexer: CypherLexer::new(src)?,
+            source_id: Some(source_id),
+            ..CypherParser::from_lexer(lexer)
         })
     }
-    pub fn token(&self, pos: usize) -> Result<(&Token<'a>, usize), ParseError<'a>> {
+    pub fn source_id(&self) -> Option<SourceId> {
+        self.source_id
+    }
+    // Every error leaving the parser is built here, so it carries `source_id`.
+    pub fn error(&self, kind: ParseErrorKind<'a>) -> ParseError<'a> {
+        ParseError {
+            kind,
+            source_id: self.source_id,
+        }
+    }
+    pub fn with_max_expression_depth(mut self, n: usize) -> Self {
+        self.max_expression_depth = Some(n);
+        self
+    }
+    pub fn token(&self, pos: usize) -> Result<(&Token<'a>, usize), ParseErrorKind<'a>> {
         self.lexer.token(pos)
     }
+    pub fn source(&self) -> &'a str {
+        self.lexer.source
+    }
+    pub fn source_slice(&self, token_start: usize, token_end: usize) -> Option<&'a str> {
+        self.lexer.source_slice(token_start, token_end)
+    }
+    pub fn lookahead(&self, pos: usize, n: usize) -> Option<&Token<'a>> {
+        self.token(pos + n).ok().map(|(t, _)| t)
+    }
+    pub fn peek_keyword(&self, pos: usize, keyword: &Token<'a>) -> bool {
+        self.lookahead(pos, 0) == Some(keyword)
+    }
+    // A starting point for a chain, e.g. `parser.seek(10).then(|p| parser.expression(p))`.
+    pub fn seek(&self, pos: usize) -> ParseResult<'a, usize> {
+        Success(pos, pos)
+    }
     pub fn one_or_more<T, Then>(&self, pos: usize, then: Then) -> ParseResult<'a, Vec<T>>
     where
         Then: FnOnce(usize) -> ParseResult<'a, T> + Copy,
     {
-        match self.zero_or_more(pos, then) {
-            Success(vals, _) if vals.is_empty() => Fail(pos),
-            other => other,
-        }
+        self.zero_or_more(pos, then).filter(|vals| !vals.is_empty())
     }
 
     pub fn zero_or_more<T, Then>(&self, pos: usize, then: Then) -> ParseResult<'a, Vec<T>>
@@ -44,18 +93,132 @@ impl<'a> CypherParser<'a> {
         }
     }
 
+    pub fn count_while<T, F>(&self, pos: usize, mut f: F) -> ParseResult<'a, usize>
+    where
+        F: FnMut(usize) -> ParseResult<'a, T>,
+    {
+        let mut count = 0;
+        let mut pos = pos;
+        loop {
+            match f(pos) {
+                Success(_, next) if next > pos => {
+                    count += 1;
+                    pos = next;
+                }
+                Success(_, _) | Fail(_) | Error(ReachedEOF(_)) => return Success(count, pos),
+                Error(e) => return Error(e),
+            }
+        }
+    }
+
+    pub fn try_all<T: Clone>(
+        &self,
+        pos: usize,
+        parsers: &[&dyn Fn(usize) -> ParseResult<'a, T>],
+    ) -> Vec<ParseResult<'a, T>> {
+        parsers
+            .iter()
+            .map(|parser| parser(pos))
+            .filter(|res| matches!(res, Success(..)))
+            .collect()
+    }
+
+    pub fn scan_forward<F>(&self, pos: usize, predicate: F) -> ParseResult<'a, usize>
+    where
+        F: Fn(&Token<'a>) -> bool,
+    {
+        let mut pos = pos;
+        loop {
+            match self.token(pos) {
+                Ok((t, idx)) if predicate(t) => return Success(idx, idx + 1),
+                Ok(_) => pos += 1,
+                Err(e) => return Error(e),
+            }
+        }
+    }
+
+    pub fn scan_to_matching_brace(&self, pos: usize) -> ParseResult<'a, usize> {
+        match self.token(pos) {
+            Ok((Token::LBrace, _)) => (),
+            Ok(_) => return Fail(pos),
+            Err(e) => return Error(e),
+        }
+        let nesting = Cell::new(0);
+        self.scan_forward(pos, |t| {
+            match t {
+                Token::LBrace => nesting.set(nesting.get() + 1),
+                Token::RBrace => nesting.set(nesting.get() - 1),
+                _ => (),
+            };
+            nesting.get() == 0
+        })
+    }
+
     pub fn validate_eof<T>(&self, res: ParseResult<'a, T>) -> ParseResult<'a, T> {
         match res {
             Success(_, pos) if self.lexer.len() != pos => ParseResult::Error(UnreachedEOF(pos)),
             other => other,
         }
     }
+    pub fn finish<T>(&self, res: ParseResult<'a, T>) -> Result<(T, usize), ParseError<'a>> {
+        match res {
+            Success(t, pos) => Ok((t, pos)),
+            Fail(pos) => Err(self.error(FinishedOnFail(pos))),
+            Error(e) => Err(self.error(e)),
+        }
+    }
+    // `f` has to consume every token.
+    pub fn parse<T, F>(&self, f: F) -> Result<T, ParseError<'a>>
+    where
+        F: FnOnce(usize) -> ParseResult<'a, T>,
+    {
+        self.finish(self.validate_eof(f(0))).map(|(t, _)| t)
+    }
+    // Like `parse`, but leftover tokens are fine:
+    // the position where `f` stopped is returned alongside the value.
+    pub fn parse_fragment<T, F>(&self, f: F) -> Result<(T, usize), ParseError<'a>>
+    where
+        F: FnOnce(usize) -> ParseResult<'a, T>,
+    {
+        self.finish(f(0))
+    }
+    // For embedding: anything that isn't valid Wren up to the end of `src` gives the default.
+    pub fn parse_or_default<T, F>(src: &'a str, f: F) -> T
+    where
+        T: Default,
+        F: FnOnce(&CypherParser<'a>) -> ParseResult<'a, T>,
+    {
+        match CypherParser::new(src) {
+            Ok(parser) => parser.validate_eof(f(&parser)).success_or_default(),
+            Err(_) => T::default(),
+        }
+    }
 }
 impl<'a> CypherParser<'a> {
+    // Fails only with `ReachedEOF` when there are no tokens left.
+    pub fn any_token(&self, pos: usize) -> ParseResult<'a, &Token<'a>> {
+        match self.token(pos) {
+            Ok((t, p)) => Success(t, p + 1),
+            Err(e) => Error(e),
+        }
+    }
+    pub fn any_token_except<F>(&self, pos: usize, predicate: F) -> ParseResult<'a, &Token<'a>>
+    where
+        F: FnOnce(&Token<'a>) -> bool,
+    {
+        match self.any_token(pos) {
+            Success(t, _) if predicate(t) => Fail(pos),
+            other => other,
+        }
+    }
+    pub fn keyword(&self, pos: usize) -> ParseResult<'a, EmptyToken> {
+        token!(self.token(pos) => t if t.is_keyword())
+    }
     pub fn id(&self, pos: usize) -> ParseResult<'a, Id<'a>> {
         token!(self.token(pos) =>
             Token::Id(value) => Id{value}
         )
+        .filter(|id| !KEYWORDS.contains(&id.value))
     }
     pub fn number(&self, pos: usize) -> ParseResult<'a, Number> {
         token!(self.token(pos) =>
@@ -82,6 +245,20 @@ impl<'a> CypherParser<'a> {
             Token::TextBlock(v) => *v
         )
     }
+    pub fn string_or_id(&self, pos: usize) -> ParseResult<'a, &'a str> {
+        self.string(pos).or(|p| self.id(p).map(|id| id.value))
+    }
+    pub fn qualified_id(&self, pos: usize) -> ParseResult<'a, Vec<Id<'a>>> {
+        self.id(pos)
+            .then_multi_zip(|p| token!(self.token(p) => Token::Dot).then(|p| self.id(p)))
+            .merge()
+    }
+    pub fn string_expr(&self, pos: usize) -> ParseResult<'a, AtomExpression<'a>> {
+        token!(self.token(pos) =>
+            Token::StringLit(v) => AtomExpression::StringLit(v),
+            Token::TextBlock(v) => AtomExpression::TextBlock(v)
+        )
+    }
     pub fn number_expr(&self, pos: usize) -> ParseResult<'a, AtomExpression<'a>> {
         self.number(pos).map(AtomExpression::Number)
     }
@@ -124,6 +301,10 @@ impl<'a> CypherParser<'a> {
     }
 
     pub fn expression(&self, pos: usize) -> ParseResult<'a, Expression<'a>> {
+        self.nested(pos, || self.expression_at(pos))
+    }
+
+    fn expression_at(&self, pos: usize) -> ParseResult<'a, Expression<'a>> {
         let not = |p| {
             token!(self.token(p) => Token::Bang)
                 .then(|p| self.expression(p))
@@ -155,6 +336,96 @@ impl<'a> CypherParser<'a> {
             .into()
     }
 
+    // Precedence climbing over the operators of `compound_expr`: an operator binding looser
+    // than `min_prec` ends the expression and its right operand is parsed one level tighter.
+    // The operands are still chained the way `expression` nests them, so with `min_prec` 0
+    // the result is the one `expression` gives.
+    pub fn expression_with_precedence(
+        &self,
+        pos: usize,
+        min_prec: u8,
+    ) -> ParseResult<'a, Expression<'a>> {
+        self.climb(pos, min_prec).map(|(e, _)| e)
+    }
+
+    // Also gives the number of steps down the right of the expression to its last operand,
+    // which is where the next operator is attached.
+    fn climb(&self, pos: usize, min_prec: u8) -> ParseResult<'a, (Expression<'a>, usize)> {
+        self.nested(pos, || self.climb_at(pos, min_prec))
+    }
+
+    fn climb_at(&self, pos: usize, min_prec: u8) -> ParseResult<'a, (Expression<'a>, usize)> {
+        let not = |p| {
+            token!(self.token(p) => Token::Bang)
+                .then(|p| self.climb(p, min_prec))
+                .map(|(e, links)| (Expression::Not(Box::new(e)), links + 1))
+        };
+        let wrapped = |p| {
+            token!(self.token(p) => Token::LParen)
+                .then(|p| self.expression(p))
+                .then_zip(|p| token!(self.token(p) => Token::RParen))
+                .take_left()
+                .map(|e| (e, 0))
+        };
+        let primary: ParseResult<(Expression, usize)> = self
+            .atom(pos)
+            .map(|a| (Expression::Atom(a), 0))
+            .or_from(pos)
+            .or(not)
+            .or(wrapped)
+            .into();
+
+        let (mut expr, mut links, mut pos) = match primary {
+            Success((e, links), p) => (e, links, p),
+            Fail(p) => return Fail(p),
+            Error(e) => return Error(e),
+        };
+        loop {
+            let operator = self.compound_expr_with(pos, |p| Success(Expression::E, p));
+            let (mut tail, op_end) = match operator {
+                Success(tail, p) if tail.operator_precedence() >= min_prec => (tail, p),
+                Success(..) | Fail(_) | Error(ReachedEOF(_)) => return Success((expr, links), pos),
+                Error(e) => return Error(e),
+            };
+            let prec = tail.operator_precedence();
+            let rhs = match tail.right_operand_mut() {
+                Some(operand) if *operand == Expression::E => match self.climb(op_end, prec + 1) {
+                    Success((e, rhs_links), p) => {
+                        *operand = e;
+                        Some((rhs_links, p))
+                    }
+                    Fail(_) | Error(ReachedEOF(_)) => return Success((expr, links), pos),
+                    Error(e) => return Error(e),
+                },
+                _ => None,
+            };
+            expr = attach(expr, links, tail);
+            match rhs {
+                Some((rhs_links, p)) => {
+                    links += 1 + rhs_links;
+                    pos = p;
+                }
+                None => return Success((expr, links), op_end),
+            }
+        }
+    }
+
+    // The limit is checked before going one expression deeper, so too deep an input fails
+    // before the recursion gets out of hand. A parenthesised expression counts as a level too.
+    fn nested<T, F>(&self, pos: usize, parse: F) -> ParseResult<'a, T>
+    where
+        F: FnOnce() -> ParseResult<'a, T>,
+    {
+        let depth = self.expression_depth.get() + 1;
+        if self.max_expression_depth.is_some_and(|max| depth > max) {
+            return Error(FailedOnValidation("expression exceeds the maximum depth", pos));
+        }
+        self.expression_depth.set(depth);
+        let res = parse();
+        self.expression_depth.set(depth - 1);
+        res
+    }
+
     pub fn enumeration(&self, pos: usize) -> ParseResult<'a, Enumeration<'a>> {
         let tail = |p| token!(self.token(p) => Token::Comma).then(|p| self.expression(p));
 
@@ -164,23 +435,34 @@ impl<'a> CypherParser<'a> {
             .map(Enumeration::new)
     }
 
+    pub fn expression_list(&self, pos: usize) -> ParseResult<'a, Vec<Expression<'a>>> {
+        self.enumeration(pos).map(|e| e.values).or_val(vec![])
+    }
+
     pub fn statement(&self, pos: usize) -> ParseResult<'a, Statement<'a>> {
         let ret = |p| {
             token!(self.token(p) => Token::Return)
                 .then(|p| self.expression(p))
                 .map(Statement::Return)
         };
-        self.assignment(pos)
-            .map(Statement::Assignment)
-            .or_from(pos)
-            .or(|p| self.assignment_null(p).map(Statement::AssignmentNull))
-            .or(|p| self.block(p).map(Statement::Block))
-            .or(|p| self.expression(p).map(Statement::Expression))
-            .or(|p| self.if_statement(p).map(Box::new).map(Statement::If))
-            .or(|p| self.while_statement(p).map(Box::new).map(Statement::While))
-            .or(|p| self.for_statement(p).map(Box::new).map(Statement::For))
-            .or(ret)
-            .into()
+        // None of the other alternatives can start with these keywords.
+        if self.peek_keyword(pos, &Token::If) {
+            self.if_statement(pos).map(Box::new).map(Statement::If)
+        } else if self.peek_keyword(pos, &Token::While) {
+            self.while_statement(pos).map(Box::new).map(Statement::While)
+        } else if self.peek_keyword(pos, &Token::For) {
+            self.for_statement(pos).map(Box::new).map(Statement::For)
+        } else if self.peek_keyword(pos, &Token::Return) {
+            ret(pos)
+        } else {
+            self.assignment(pos)
+                .map(Statement::Assignment)
+                .or_from(pos)
+                .or(|p| self.assignment_null(p).map(Statement::AssignmentNull))
+                .or(|p| self.block(p).map(Statement::Block))
+                .or(|p| self.expression(p).map(Statement::Expression))
+                .into()
+        }
     }
     pub fn file_unit(&self, pos: usize) -> ParseResult<'a, Unit<'a>> {
         self.class_def(pos)
@@ -198,21 +480,58 @@ impl<'a> CypherParser<'a> {
             .map(|units| Script { units })
     }
 
+    // The ast keeps no spans, so the units of `script` (parsed by this parser) are
+    // walked again to find the token each one starts at.
+    pub fn position_of_class(&self, script: &Script<'a>, name: &str) -> Option<usize> {
+        let start =
+            self.unit_position(script, |u| matches!(u, Unit::Class(c) if c.name.value == name))?;
+        (start..)
+            .map_while(|p| self.lookahead(p, 0).map(|t| (p, t)))
+            .find(|(_, t)| *t == &Token::Class)
+            .map(|(p, _)| p)
+    }
+    pub fn position_of_function(&self, script: &Script<'a>, name: &str) -> Option<usize> {
+        self.unit_position(script, |u| matches!(u, Unit::Fn(f) if f.name.value == name))
+    }
+    // `module_name` may be given with or without the quotes.
+    pub fn position_of_import(&self, script: &Script<'a>, module_name: &str) -> Option<usize> {
+        let module_name = strip_quotes(module_name);
+        self.unit_position(script, |u| {
+            matches!(u, Unit::Import(i) if strip_quotes(i.name) == module_name)
+        })
+    }
+    fn unit_position<P>(&self, script: &Script<'a>, pred: P) -> Option<usize>
+    where
+        P: Fn(&Unit<'a>) -> bool,
+    {
+        let mut pos = 0;
+        for unit in &script.units {
+            if pred(unit) {
+                return Some(pos);
+            }
+            match self.file_unit(pos) {
+                Success(_, next) => pos = next,
+                _ => return None,
+            }
+        }
+        None
+    }
+
     pub fn assignment(&self, pos: usize) -> ParseResult<'a, Assignment<'a>> {
         let op = |p| {
             token!(self.token(p) =>
                 Token::Assign => AssignOp::Assign,
-                Token::MultAssign => AssignOp::Sub,
+                Token::SubAssign => AssignOp::Sub,
+                Token::MultAssign => AssignOp::Mul,
                 Token::AddAssign => AssignOp::Add,
                 Token::DivAssign => AssignOp::Div,
                 Token::AndAssign => AssignOp::And,
                 Token::OrAssign => AssignOp::Or,
                 Token::XOrAssign => AssignOp::Xor,
                 Token::ModAssign => AssignOp::Mod,
-                Token::LShift => AssignOp::LShift,
-                Token::RShift => AssignOp::RShift,
-                Token::URShiftAssign => AssignOp::URShift,
-                Token::SubAssign => AssignOp::Mul
+                Token::LShiftAssign => AssignOp::LShift,
+                Token::RShiftAssign => AssignOp::RShift,
+                Token::URShiftAssign => AssignOp::URShift
             )
         };
 
@@ -249,6 +568,13 @@ impl<'a> CypherParser<'a> {
             .map(|id| AssignmentNull { id })
     }
 
+    pub fn block_or_single(&self, pos: usize) -> ParseResult<'a, BlockOrStatement<'a>> {
+        self.block(pos)
+            .map(BlockOrStatement::Block)
+            .or_from(pos)
+            .or(|p| self.statement(p).map(Box::new).map(BlockOrStatement::Single))
+            .into()
+    }
     pub fn if_statement(&self, pos: usize) -> ParseResult<'a, If<'a>> {
         let main = |p| {
             token!(self.token(p) => Token::If)
@@ -256,7 +582,7 @@ impl<'a> CypherParser<'a> {
                 .then(|p| self.expression(p))
                 .then_zip(|p| token!(self.token(p) => Token::RParen))
                 .take_left()
-                .then_zip(|p| self.statement(p))
+                .then_zip(|p| self.block_or_single(p))
                 .map(|(cond, action)| IfBranch { cond, action })
         };
 
@@ -265,7 +591,7 @@ impl<'a> CypherParser<'a> {
 
         let else_opt = |p| {
             token!(self.token(p) => Token::Else)
-                .then(|p| self.statement(p))
+                .then(|p| self.block_or_single(p))
                 .or_none()
         };
 
@@ -273,6 +599,7 @@ impl<'a> CypherParser<'a> {
             .then_zip(else_ifs)
             .then_or_none_zip(else_opt)
             .map(|((main, others), els)| If { main, others, els })
+            .contextualize_error("if statement")
     }
 
     pub fn block(&self, pos: usize) -> ParseResult<'a, Block<'a>> {
@@ -324,6 +651,21 @@ impl<'a> CypherParser<'a> {
             })
     }
 
+    // The call is parsed once and a `[` behind it decides whether it is indexed,
+    // `collection_elem` would parse it a second time for a plain call.
+    fn call_or_collection_elem(&self, pos: usize) -> ParseResult<'a, AtomExpression<'a>> {
+        match self.call(pos) {
+            Success(call, next) if self.lookahead(next, 0) == Some(&Token::LBrack) => {
+                match self.list_init(next) {
+                    Success(e, end) => Success(AtomExpression::CollectionElem(call, e), end),
+                    Fail(_) | Error(ReachedEOF(_)) => Success(AtomExpression::Call(call), next),
+                    Error(e) => Error(e),
+                }
+            }
+            other => other.map(AtomExpression::Call),
+        }
+    }
+
     pub fn collection_elem(&self, pos: usize) -> ParseResult<'a, AtomExpression<'a>> {
         self.string(pos)
             .map(Call::just_id)
@@ -360,6 +702,14 @@ impl<'a> CypherParser<'a> {
             self.call(p)
                 .map(RangeExpression::Call)
                 .or(|p| self.number(p).map(RangeExpression::Num))
+                .ok()
+                .or(|p| {
+                    self.string(p)
+                        .map(|_| None)
+                        .or(|p| self.char(p).map(|_| None))
+                        .or(|p| self.null(p).map(|_| None))
+                        .or(|p| self.bool(p).map(|_| None))
+                })
         };
         let ellipsis = |p| {
             token!(self.token(p) =>
@@ -367,15 +717,23 @@ impl<'a> CypherParser<'a> {
                 Token::EllipsisOut => true
             )
         };
-        let to_range = |((left, is_out), right)| Range {
-            left,
-            right,
+        let validate_endpoints = |((left, _), right): &((Option<_>, _), Option<_>)| {
+            if left.is_some() && right.is_some() {
+                Ok(())
+            } else {
+                Err("range endpoints must be numeric or identifier")
+            }
+        };
+        let to_range = |((left, is_out), right): ((Option<_>, _), Option<_>)| Range {
+            left: left.unwrap(),
+            right: right.unwrap(),
             is_out,
         };
 
         range_expr(pos)
             .then_zip(ellipsis)
             .then_zip(range_expr)
+            .validate(validate_endpoints)
             .map(to_range)
     }
 
@@ -391,13 +749,12 @@ impl<'a> CypherParser<'a> {
             .or(|p| self.import_module(p).map(AtomExpression::ImportModule))
             .or(|p| self.range(p).map(AtomExpression::Range))
             .or(|p| self.char(p))
-            .or(|p| self.string(p).map(AtomExpression::StringLit))
+            .or(|p| self.string_expr(p))
             .or(|p| self.number(p).map(AtomExpression::Number))
             .or(|p| self.null(p))
             .or(|p| self.list_init(p).map(AtomExpression::ListInit))
             .or(|p| self.map_init(p))
-            .or(|p| self.collection_elem(p))
-            .or(|p| self.call(p).map(AtomExpression::Call))
+            .or(|p| self.call_or_collection_elem(p))
             .or(|p| token!(self.token(p) => Token::Break => AtomExpression::Break))
             .or(|p| token!(self.token(p) => Token::Continue => AtomExpression::Continue))
             .or(with_sub)
@@ -421,9 +778,24 @@ impl<'a> CypherParser<'a> {
             .then_zip(params)
             .then_or_none_zip(|p| self.block(p).or_none())
             .map(to_fn)
+            .contextualize_error("function")
+    }
+
+    pub fn function_list(&self, pos: usize) -> ParseResult<'a, Vec<Function<'a>>> {
+        self.function(pos)
+            .then_multi_zip(|p| token!(self.token(p) => Token::Comma).then(|p| self.function(p)))
+            .merge()
+            .or_val(vec![])
     }
 
     pub fn logic_atom(&self, pos: usize) -> ParseResult<'a, Logic<'a>> {
+        self.logic_atom_with(pos, |p| self.expression(p))
+    }
+
+    fn logic_atom_with<R>(&self, pos: usize, rhs: R) -> ParseResult<'a, Logic<'a>>
+    where
+        R: Fn(usize) -> ParseResult<'a, Expression<'a>> + Copy,
+    {
         token!(self.token(pos) =>
             Token::Or => LogicOp::Or,
             Token::Gt => LogicOp::Gt,
@@ -434,11 +806,18 @@ impl<'a> CypherParser<'a> {
             Token::Le => LogicOp::Le,
             Token::And => LogicOp::And
         )
-        .then_zip(|p| self.expression(p))
+        .then_zip(rhs)
         .map(|(op, value)| Logic::Atom(op, value))
     }
 
     pub fn compound_expr(&self, pos: usize) -> ParseResult<'a, CompoundExpression<'a>> {
+        self.compound_expr_with(pos, |p| self.expression(p))
+    }
+
+    fn compound_expr_with<R>(&self, pos: usize, rhs: R) -> ParseResult<'a, CompoundExpression<'a>>
+    where
+        R: Fn(usize) -> ParseResult<'a, Expression<'a>> + Copy,
+    {
         let tail = |p| {
             token!(self.token(p) => Token::Dot)
                 .then(|p| self.call(p))
@@ -447,12 +826,12 @@ impl<'a> CypherParser<'a> {
 
         let is = |p| {
             token!(self.token(p) => Token::Is)
-                .then(|p| self.expression(p))
+                .then(rhs)
                 .map(Box::new)
                 .map(CompoundExpression::Is)
         };
-        let logic = self.logic(pos).map(CompoundExpression::Logic);
-        let arithmetic = |p| self.arith(p).map(CompoundExpression::Arith);
+        let logic = self.logic_with(pos, rhs).map(CompoundExpression::Logic);
+        let arithmetic = |p| self.arith_with(p, rhs).map(CompoundExpression::Arith);
         let elvis = |p| self.elvis(p).map(CompoundExpression::Elvis);
 
         logic
@@ -465,12 +844,19 @@ impl<'a> CypherParser<'a> {
     }
 
     pub fn logic(&self, pos: usize) -> ParseResult<'a, Logic<'a>> {
+        self.logic_with(pos, |p| self.expression(p))
+    }
+
+    fn logic_with<R>(&self, pos: usize, rhs: R) -> ParseResult<'a, Logic<'a>>
+    where
+        R: Fn(usize) -> ParseResult<'a, Expression<'a>> + Copy,
+    {
         let and = |p| {
-            self.logic_atom(p)
+            self.logic_atom_with(p, rhs)
                 .then_multi_zip(|p| {
                     token!(self.token(p) => Token::And)
-                        .then(|p| self.expression(p))
-                        .then_zip(|p| self.logic_atom(p))
+                        .then(rhs)
+                        .then_zip(|p| self.logic_atom_with(p, rhs))
                         .map(|(e, l)| (e, Box::new(l)))
                 })
                 .map(|(l, tail)| {
@@ -484,7 +870,7 @@ impl<'a> CypherParser<'a> {
         and(pos)
             .then_multi_zip(|p| {
                 token!(self.token(p) => Token::Or)
-                    .then(|p| self.expression(p))
+                    .then(rhs)
                     .then_zip(and)
                     .map(|(e, l)| (e, Box::new(l)))
             })
@@ -497,13 +883,20 @@ impl<'a> CypherParser<'a> {
             })
     }
     pub fn arith(&self, pos: usize) -> ParseResult<'a, Arithmetic<'a>> {
+        self.arith_with(pos, |p| self.expression(p))
+    }
+
+    fn arith_with<R>(&self, pos: usize, rhs: R) -> ParseResult<'a, Arithmetic<'a>>
+    where
+        R: Fn(usize) -> ParseResult<'a, Expression<'a>> + Copy,
+    {
         let mul = |p| {
             token!(self.token(p) =>
                         Token::Mult => MulSign::Mul,
                         Token::Div => MulSign::Div,
                         Token::Mod => MulSign::Mod
             )
-            .then_zip(|p| self.expression(p))
+            .then_zip(rhs)
             .map(|(s, e)| Arithmetic::Mul(s, e))
         };
         let add = |p| {
@@ -511,7 +904,7 @@ impl<'a> CypherParser<'a> {
                         Token::Sub => false,
                         Token::Add => true
             )
-            .then_zip(|p| mul(p).or(|p| self.expression(p).map(Arithmetic::Expression)))
+            .then_zip(|p| mul(p).or(|p| rhs(p).map(Arithmetic::Expression)))
             .map(|(s, e)| Arithmetic::Add(s, Box::new(e)))
         };
         let range = |p| {
@@ -519,7 +912,7 @@ impl<'a> CypherParser<'a> {
                         Token::EllipsisIn => false,
                         Token::EllipsisOut => true
             )
-            .then_zip(|p| add(p).or(|p| self.expression(p).map(Arithmetic::Expression)))
+            .then_zip(|p| add(p).or(|p| rhs(p).map(Arithmetic::Expression)))
             .map(|(s, e)| Arithmetic::Range(s, Box::new(e)))
         };
         let shift = |p| {
@@ -527,7 +920,7 @@ impl<'a> CypherParser<'a> {
                         Token::LShift => false,
                         Token::RShift => true
             )
-            .then_zip(|p| range(p).or(|p| self.expression(p).map(Arithmetic::Expression)))
+            .then_zip(|p| range(p).or(|p| rhs(p).map(Arithmetic::Expression)))
             .map(|(s, e)| Arithmetic::Shift(s, Box::new(e)))
         };
         let bit = |p| {
@@ -536,7 +929,7 @@ impl<'a> CypherParser<'a> {
                         Token::BitAnd => BitSign::And,
                         Token::Caret => BitSign::Xor
             )
-            .then_zip(|p| shift(p).or(|p| self.expression(p).map(Arithmetic::Expression)))
+            .then_zip(|p| shift(p).or(|p| rhs(p).map(Arithmetic::Expression)))
             .map(|(s, e)| Arithmetic::Bit(s, Box::new(e)))
         };
 
@@ -633,19 +1026,19 @@ impl<'a> CypherParser<'a> {
         let foreign = |p| token!(self.token(p) => Token::Foreign => ClassBodyType::Foreign);
         let static_t = |p| token!(self.token(p) => Token::Static => ClassBodyType::Static);
 
+        let with = |tpe: ClassBodyType, other: ClassBodyType| match other {
+            ClassBodyType::None => tpe,
+            _ => ClassBodyType::ForeignStatic,
+        };
         let tpe = |p| {
             foreign(p)
-                .then(static_t)
-                .map(|r| ClassBodyType::ForeignStatic)
-                .or_from(p)
+                .then_or_default_zip(static_t)
+                .map(|(f, s)| with(f, s))
                 .or(|p| {
                     static_t(p)
-                        .then(foreign)
-                        .map(|r| ClassBodyType::ForeignStatic)
+                        .then_or_default_zip(foreign)
+                        .map(|(s, f)| with(s, f))
                 })
-                .or(static_t)
-                .or(foreign)
-                .into()
         };
 
         self.zero_or_more(pos, |p| self.attribute(p))
@@ -658,6 +1051,13 @@ impl<'a> CypherParser<'a> {
             })
     }
 
+    pub fn class_body_list(&self, pos: usize) -> ParseResult<'a, Vec<ClassUnit<'a>>> {
+        token!(self.token(pos) => Token::LBrace)
+            .then(|p| self.zero_or_more(p, |p| self.class_body(p)))
+            .then_zip(|p| token!(self.token(p) => Token::RBrace))
+            .take_left()
+    }
+
     pub fn attribute(&self, pos: usize) -> ParseResult<'a, Attribute<'a>> {
         let prefix = |p| {
             token!(self.token(p) => Token::Hash)
@@ -717,8 +1117,9 @@ impl<'a> CypherParser<'a> {
             .then(cond)
             .then_zip(|p| token!(self.token(p) => Token::RParen))
             .take_left()
-            .then_zip(|p| self.statement(p))
+            .then_zip(|p| self.block_or_single(p))
             .map(|(cond, body)| While { cond, body })
+            .contextualize_error("while statement")
     }
     pub fn for_statement(&self, pos: usize) -> ParseResult<'a, For<'a>> {
         token!(self.token(pos) => Token::For)
@@ -729,7 +1130,7 @@ impl<'a> CypherParser<'a> {
             .then_zip(|p| self.expression(p))
             .then_zip(|p| token!(self.token(p) => Token::RParen))
             .take_left()
-            .then_zip(|p| self.statement(p))
+            .then_zip(|p| self.block_or_single(p))
             .map(|((elem, collection), body)| For {
                 elem,
                 collection,
@@ -746,11 +1147,7 @@ impl<'a> CypherParser<'a> {
             .take_left()
             .then_zip(|p| self.id(p))
             .then_or_none_zip(|p| inherit(p).or_none())
-            .then_zip(|p| token!(self.token(p) => Token::LBrace))
-            .take_left()
-            .then_zip(|p| self.zero_or_more(p, |p| self.class_body(p)))
-            .then_zip(|p| token!(self.token(p) => Token::RBrace))
-            .take_left()
+            .then_zip(|p| self.class_body_list(p))
             .map(|((((attrs, f), name), inherit), elems)| ClassDefinition {
                 attributes: attrs,
                 foreign: f,
@@ -758,5 +1155,24 @@ impl<'a> CypherParser<'a> {
                 inherit,
                 elems,
             })
+            .contextualize_error("class definition")
+    }
+    pub fn class_def_list(&self, pos: usize) -> ParseResult<'a, Vec<ClassDefinition<'a>>> {
+        self.zero_or_more(pos, |p| self.class_def(p))
+    }
+}
+
+// Attaches `tail` to the operand `links` steps down the right of `expr`.
+fn attach<'a>(expr: Expression<'a>, links: usize, tail: CompoundExpression<'a>) -> Expression<'a> {
+    match expr {
+        Expression::Not(e) if links > 0 => Expression::Not(Box::new(attach(*e, links - 1, tail))),
+        Expression::Compound(lhs, mut rest) if links > 0 => {
+            if let Some(operand) = rest.right_operand_mut() {
+                let e = std::mem::replace(operand, Expression::E);
+                *operand = attach(e, links - 1, tail);
+            }
+            Expression::Compound(lhs, rest)
+        }
+        e => Expression::Compound(Box::new(e), Box::new(tail)),
     }
 }
@@ -0,0 +1,282 @@
+use crate::parser::ast::*;
+
+const INDENT: &str = "  ";
+
+// The tree keeps no parentheses: a compound only ends up on the left of another compound
+// when it was wrapped, so that is the one place they may have to be put back.
+pub fn expression(expr: &Expression, indent: usize) -> String {
+    match expr {
+        Expression::Atom(a) => atom(a, indent),
+        Expression::Compound(lhs, tail) => {
+            let lhs = if needs_parens(lhs, tail) {
+                format!("({})", expression(lhs, indent))
+            } else {
+                expression(lhs, indent)
+            };
+            match tail.as_ref() {
+                CompoundExpression::Tail(c) => format!("{}.{}", lhs, call(c, indent)),
+                tail => format!("{} {}", lhs, compound(tail, indent)),
+            }
+        }
+        Expression::Not(e) => match e.as_ref() {
+            e @ Expression::Compound(..) => format!("!({})", expression(e, indent)),
+            e => format!("!{}", expression(e, indent)),
+        },
+        Expression::E => String::new(),
+    }
+}
+
+pub fn arithmetic(arith: &Arithmetic, indent: usize) -> String {
+    let (op, operand) = match arith {
+        Arithmetic::Expression(e) => return expression(e, indent),
+        Arithmetic::Mul(sign, e) => {
+            let op = match sign {
+                MulSign::Mul => "*",
+                MulSign::Div => "/",
+                MulSign::Mod => "%",
+            };
+            return format!("{} {}", op, expression(e, indent));
+        }
+        Arithmetic::Add(plus, a) => (if *plus { "+" } else { "-" }, a),
+        Arithmetic::Range(out, a) => (if *out { "..." } else { ".." }, a),
+        Arithmetic::Shift(right, a) => (if *right { ">>" } else { "<<" }, a),
+        Arithmetic::Bit(sign, a) => {
+            let op = match sign {
+                BitSign::And => "&",
+                BitSign::Or => "|",
+                BitSign::Xor => "^",
+            };
+            (op, a)
+        }
+    };
+    format!("{} {}", op, arithmetic(operand, indent))
+}
+
+pub fn statement(stmt: &Statement, indent: usize) -> String {
+    match stmt {
+        Statement::Expression(e) => expression(e, indent),
+        Statement::Assignment(a) => assignment(a, indent),
+        Statement::AssignmentNull(a) => format!("var {}", a.id.value),
+        Statement::If(i) => {
+            let branch = |b: &IfBranch| {
+                format!(
+                    "if ({}) {}",
+                    expression(&b.cond, indent),
+                    body(&b.action, indent)
+                )
+            };
+            let mut res = branch(&i.main);
+            for b in &i.others {
+                res = format!("{} else {}", res, branch(b));
+            }
+            match &i.els {
+                Some(els) => format!("{} else {}", res, body(els, indent)),
+                None => res,
+            }
+        }
+        Statement::While(w) => {
+            let cond = match &w.cond {
+                WhileCond::Expression(e) => expression(e, indent),
+                WhileCond::Assignment(a) => assignment(a, indent),
+            };
+            format!("while ({}) {}", cond, body(&w.body, indent))
+        }
+        Statement::For(f) => format!(
+            "for ({} in {}) {}",
+            f.elem.value,
+            expression(&f.collection, indent),
+            body(&f.body, indent)
+        ),
+        Statement::Block(b) => block(b, indent),
+        Statement::Return(e) => format!("return {}", expression(e, indent)),
+    }
+}
+
+// A block holding a single expression stays on one line (`{ |x| x * 2 }`).
+pub fn block(b: &Block, indent: usize) -> String {
+    let params = if b.params.ids.is_empty() {
+        String::new()
+    } else {
+        format!(" |{}|", join(b.param_names().map(str::to_string)))
+    };
+    match b.statements.as_slice() {
+        [] if params.is_empty() => "{}".to_string(),
+        [] => format!("{{{} }}", params),
+        [Statement::Expression(e)] => format!("{{{} {} }}", params, expression(e, indent)),
+        statements => {
+            let inner = INDENT.repeat(indent + 1);
+            let lines: Vec<_> = statements
+                .iter()
+                .map(|s| format!("{}{}", inner, statement(s, indent + 1)))
+                .collect();
+            format!(
+                "{{{}\n{}\n{}}}",
+                params,
+                lines.join("\n"),
+                INDENT.repeat(indent)
+            )
+        }
+    }
+}
+
+pub fn attribute(attr: &Attribute, indent: usize) -> String {
+    let value = |v: &AttributeValue| match &v.expr {
+        Some(e) => format!("{} = {}", v.id.value, atom(e, indent)),
+        None => v.id.value.to_string(),
+    };
+    let prefix = if attr.is_negated() { "#!" } else { "#" };
+    match attr {
+        Attribute::Simple(_, v) => format!("{}{}", prefix, value(v)),
+        Attribute::Group(_, id, vs) => {
+            format!("{}{}({})", prefix, id.value, join(vs.iter().map(value)))
+        }
+    }
+}
+
+// `(a * b) + c` reads the same without the parentheses, `(a + b) * c` doesn't.
+fn needs_parens(lhs: &Expression, tail: &CompoundExpression) -> bool {
+    match lhs {
+        Expression::Not(_) => true,
+        Expression::Compound(..) => {
+            let (inner, outer) = (lhs.loosest_precedence(), tail.operator_precedence());
+            inner < outer || inner == outer && !tail.is_left_associative()
+        }
+        _ => false,
+    }
+}
+
+fn body(b: &BlockOrStatement, indent: usize) -> String {
+    match b {
+        BlockOrStatement::Block(b) => block(b, indent),
+        BlockOrStatement::Single(s) => statement(s, indent),
+    }
+}
+
+fn assignment(a: &Assignment, indent: usize) -> String {
+    let rhs = match a.rhs.as_ref() {
+        Rhs::Expression(e) => expression(e, indent),
+        Rhs::Assignment(a) => assignment(a, indent),
+        Rhs::Assignments(v) => v
+            .iter()
+            .map(|a| assignment(a, indent))
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+    let var = if a.var { "var " } else { "" };
+    format!("{}{} {} {}", var, expression(&a.lhs, indent), a.op.to_symbol(), rhs)
+}
+
+fn compound(tail: &CompoundExpression, indent: usize) -> String {
+    match tail {
+        CompoundExpression::Logic(l) => logic(l, indent),
+        CompoundExpression::Arith(a) => arithmetic(a, indent),
+        CompoundExpression::Tail(c) => format!(".{}", call(c, indent)),
+        CompoundExpression::Is(e) => format!("is {}", expression(e, indent)),
+        CompoundExpression::Elvis(e) => format!(
+            "? {} : {}",
+            expression(&e.lhs, indent),
+            expression(&e.rhs, indent)
+        ),
+    }
+}
+
+fn logic(l: &Logic, indent: usize) -> String {
+    let chain = |l: &Logic, tail: &[(Expression, Box<Logic>)], op: &str| {
+        tail.iter().fold(logic(l, indent), |res, (e, l)| {
+            format!("{} {} {} {}", res, op, expression(e, indent), logic(l, indent))
+        })
+    };
+    match l {
+        Logic::Atom(op, e) => {
+            let op = match op {
+                LogicOp::Gt => ">",
+                LogicOp::Lt => "<",
+                LogicOp::Eq => "==",
+                LogicOp::Le => "<=",
+                LogicOp::Ge => ">=",
+                LogicOp::NotEq => "!=",
+                LogicOp::Or => "||",
+                LogicOp::And => "&&",
+            };
+            format!("{} {}", op, expression(e, indent))
+        }
+        Logic::And(l, tail) => chain(l, tail, "&&"),
+        Logic::Or(l, tail) => chain(l, tail, "||"),
+    }
+}
+
+fn atom(a: &AtomExpression, indent: usize) -> String {
+    match a {
+        AtomExpression::Null => "null".to_string(),
+        AtomExpression::Bool(b) => b.to_string(),
+        AtomExpression::CharLit(v) | AtomExpression::StringLit(v) | AtomExpression::TextBlock(v) => {
+            v.to_string()
+        }
+        AtomExpression::Number(n) => number(n),
+        AtomExpression::MapInit(pairs) => format!(
+            "{{{}}}",
+            join(pairs.iter().map(|(k, v)| {
+                format!("{}: {}", expression(k, indent), expression(v, indent))
+            }))
+        ),
+        AtomExpression::ListInit(e) => format!("[{}]", enumeration(e, indent)),
+        AtomExpression::Call(c) => call(c, indent),
+        AtomExpression::Range(r) => {
+            let end = |e: &RangeExpression| match e {
+                RangeExpression::Call(c) => call(c, indent),
+                RangeExpression::Num(n) => number(n),
+            };
+            let op = if r.is_out { "..." } else { ".." };
+            format!("{}{}{}", end(&r.left), op, end(&r.right))
+        }
+        AtomExpression::Break => "break".to_string(),
+        AtomExpression::Continue => "continue".to_string(),
+        AtomExpression::CollectionElem(c, e) => {
+            format!("{}[{}]", call(c, indent), enumeration(e, indent))
+        }
+        AtomExpression::ImportModule(i) => import_module(i),
+        // `--x` would be lexed as a decrement and `-1` as a negative number.
+        AtomExpression::Sub(a) => match a.as_ref() {
+            a @ (AtomExpression::Sub(_) | AtomExpression::Number(_)) => {
+                format!("- {}", atom(a, indent))
+            }
+            a => format!("-{}", atom(a, indent)),
+        },
+    }
+}
+
+fn call(c: &Call, indent: usize) -> String {
+    let middle = match &c.middle {
+        BlockOrEnum::Block(b) => format!(" {}", block(b, indent)),
+        BlockOrEnum::Enum(e) => format!("({})", enumeration(e, indent)),
+        BlockOrEnum::None => String::new(),
+    };
+    match &c.tail {
+        Some(tail) => format!("{}{}.{}", c.id.value, middle, call(tail, indent)),
+        None => format!("{}{}", c.id.value, middle),
+    }
+}
+
+fn import_module(i: &ImportModule) -> String {
+    let variable = |v: &ImportVariable| match &v.alias {
+        Some(alias) => format!("{} as {}", v.name.value, alias.value),
+        None => v.name.value.to_string(),
+    };
+    if i.variables.is_empty() {
+        format!("import {}", i.name)
+    } else {
+        format!("import {} for {}", i.name, join(i.variables.iter().map(variable)))
+    }
+}
+
+fn enumeration(e: &Enumeration, indent: usize) -> String {
+    join(e.values.iter().map(|v| expression(v, indent)))
+}
+
+fn number(n: &Number) -> String {
+    n.to_wren_literal().unwrap_or_else(|_| n.as_f64().to_string())
+}
+
+fn join<I: Iterator<Item = String>>(items: I) -> String {
+    items.collect::<Vec<_>>().join(", ")
+}
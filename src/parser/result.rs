@@ -1,18 +1,18 @@
 use crate::parser::lexer::Token;
-use crate::parser::ParseError;
+use crate::parser::{ParseError, ParseErrorKind};
 use std::borrow::Borrow;
 use std::cmp::max;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::hash::Hash;
-use ParseError::{FailedOnValidation, ReachedEOF};
+use ParseErrorKind::{BadToken, FailedOnValidation, ReachedEOF};
 use ParseResult::{Error, Fail, Success};
 
 #[derive(Debug, Clone)]
 pub enum ParseResult<'a, T> {
     Success(T, usize),
     Fail(usize),
-    Error(ParseError<'a>),
+    Error(ParseErrorKind<'a>),
 }
 
 impl<'a, L, R> ParseResult<'a, (L, R)> {
@@ -196,6 +196,10 @@ impl<'a, T> ParseResult<'a, T> {
     {
         self.then_or_val(then, None)
     }
+    // Same check as `filter`, meant as a gate between two steps of a chain.
+    pub fn then_if<F: FnOnce(&T) -> bool>(self, predicate: F) -> ParseResult<'a, T> {
+        self.filter(predicate)
+    }
 }
 impl<'a, Rhs: Debug, Lhs: Debug> ParseResult<'a, (Lhs, Rhs)> {
     pub fn debug1_show_last(self, prefix: &'a str) -> ParseResult<'a, (Lhs, Rhs)> {
@@ -265,9 +269,86 @@ impl<'a, T: Debug> ParseResult<'a, T> {
 }
 
 impl<'a, T> ParseResult<'a, T> {
+    /// Calls `f` with the result and hands the result back untouched,
+    /// so it can be inspected in the middle of a chain.
+    ///
+    /// ```
+    /// use rusty_wren::parse::{CypherParser, ParseResult};
+    ///
+    /// let parser = CypherParser::new("+ 1").unwrap();
+    /// let res = parser.expression(0).tap(|r| {
+    ///     if let ParseResult::Fail(pos) = r {
+    ///         eprintln!("expression failed at {}", pos)
+    ///     }
+    /// });
+    /// assert!(matches!(res, ParseResult::Fail(0)));
+    /// ```
+    pub fn tap<F: FnOnce(&Self)>(self, f: F) -> Self {
+        f(&self);
+        self
+    }
     pub fn ok(self) -> ParseResult<'a, Option<T>> {
         self.map(|x| Some(x))
     }
+    pub fn success_or_default(self) -> T
+    where
+        T: Default,
+    {
+        self.success_or_else(T::default)
+    }
+    pub fn success_or_else<F: FnOnce() -> T>(self, f: F) -> T {
+        match self {
+            Success(t, _) => t,
+            _ => f(),
+        }
+    }
+    pub fn is_success(&self) -> bool {
+        matches!(self, Success(..))
+    }
+    pub fn is_fail(&self) -> bool {
+        matches!(self, Fail(_))
+    }
+    pub fn is_error(&self) -> bool {
+        matches!(self, Error(_))
+    }
+    // Where a success ended or a fail happened.
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            Success(_, pos) | Fail(pos) => Some(*pos),
+            Error(_) => None,
+        }
+    }
+    pub fn end_position(self) -> Result<usize, ParseErrorKind<'a>> {
+        match self {
+            Success(_, pos) | Fail(pos) => Ok(pos),
+            Error(e) => Err(e),
+        }
+    }
+    pub fn error_message(&self) -> Option<String> {
+        match self {
+            Error(e) => Some(e.message()),
+            _ => None,
+        }
+    }
+    pub fn flat_map_error<F>(self, f: F) -> ParseResult<'a, T>
+    where
+        F: FnOnce(ParseErrorKind<'a>) -> ParseResult<'a, T>,
+    {
+        match self {
+            Error(e) => f(e),
+            other => other,
+        }
+    }
+    // `ReachedEOF` is how the alternatives backtrack, so it is passed on untouched.
+    pub fn contextualize_error(self, context: &'a str) -> ParseResult<'a, T> {
+        self.flat_map_error(|e| match e {
+            ReachedEOF(_) => Error(e),
+            e => Error(ParseErrorKind::Contextualised {
+                context,
+                inner: Box::new(e),
+            }),
+        })
+    }
     pub fn map<Rhs, Map>(self, mapper: Map) -> ParseResult<'a, Rhs>
     where
         Map: FnOnce(T) -> Rhs,
@@ -293,6 +374,21 @@ impl<'a, T> ParseResult<'a, T> {
             (Fail(pos), _) | (_, Fail(pos)) => Fail(pos),
         }
     }
+    // Both results are usually parsed from the same position, unlike with `then_zip`.
+    // Unlike `combine`, a fail is reported at the furthest of the two positions.
+    pub fn and<R>(self, other: ParseResult<'a, R>) -> ParseResult<'a, (T, R)> {
+        match (self, other) {
+            (Success(t, l), Success(r, k)) => Success((t, r), max(l, k)),
+            (Error(e), _) | (_, Error(e)) => Error(e),
+            (Success(_, l) | Fail(l), Success(_, r) | Fail(r)) => Fail(max(l, r)),
+        }
+    }
+    pub fn filter<F: FnOnce(&T) -> bool>(self, predicate: F) -> ParseResult<'a, T> {
+        match self {
+            Success(r, pos) if !predicate(&r) => Fail(pos),
+            other => other,
+        }
+    }
     pub fn validate<Validation>(self, validate: Validation) -> ParseResult<'a, T>
     where
         Validation: FnOnce(&T) -> Result<(), &'a str>,
@@ -307,6 +403,19 @@ impl<'a, T> ParseResult<'a, T> {
     }
 }
 
+impl<'a> ParseResult<'a, ()> {
+    pub fn not<T, F>(pos: usize, f: F) -> ParseResult<'a, ()>
+    where
+        F: Fn(usize) -> ParseResult<'a, T>,
+    {
+        match f(pos) {
+            Success(_, _) => Fail(pos),
+            Fail(_) | Error(ReachedEOF(_)) => Success((), pos),
+            Error(e) => Error(e),
+        }
+    }
+}
+
 impl<'a, T> ParseResult<'a, T> {
     pub fn or_val(self, default: T) -> ParseResult<'a, T> {
         match self {
@@ -315,6 +424,24 @@ impl<'a, T> ParseResult<'a, T> {
             other => other,
         }
     }
+    // The counterpart of `or_val` for an `Error`; a `Fail` is left to `or_val` and passes through.
+    // The parsing goes on `skip_tokens` past the token the error was found at, with `default`
+    // as the result. A `BadToken` knows only its byte span in the source, not a token position,
+    // so for it `skip_tokens` is the position to go on from. Running out of tokens is kept as
+    // an error, there is nothing left to skip.
+    pub fn recover_with(self, default: T, skip_tokens: usize) -> ParseResult<'a, T> {
+        match self {
+            Error(e) => match e.inner() {
+                ReachedEOF(_) => Error(e),
+                BadToken(..) => Success(default, skip_tokens),
+                _ => match e.position() {
+                    Some(pos) => Success(default, pos + skip_tokens),
+                    None => Error(e),
+                },
+            },
+            other => other,
+        }
+    }
     pub fn or_none(self) -> ParseResult<'a, Option<T>> {
         self.map(|x| Some(x)).or_val(None)
     }
@@ -378,8 +505,8 @@ impl<'a, T> Into<Result<T, ParseError<'a>>> for ParseResult<'a, T> {
     fn into(self) -> Result<T, ParseError<'a>> {
         match self {
             Success(t, _) => Ok(t),
-            Fail(_) => Err(ParseError::FinishedOnFail),
-            Error(e) => Err(e),
+            Fail(pos) => Err(ParseErrorKind::FinishedOnFail(pos).into()),
+            Error(e) => Err(e.into()),
         }
     }
 }
@@ -0,0 +1,340 @@
+use crate::parser::analysis::{
+    class_hierarchy, complexity, extract_doc_comment, is_effectful, negate, negate_expression, superclass_chain,
+    useless_expressions, validate_class_def, ComplexityMetrics, CycleError, SemanticError,
+};
+use crate::parser::ast::{
+    ClassStatement, CompoundExpression, Expression, Logic, LogicOp, Script, Statement, Unit,
+};
+use crate::parser::tests::{parser, success};
+
+fn script(src: &str) -> Script<'_> {
+    success(parser(src).script(0))
+}
+
+fn expression(src: &str) -> Expression<'_> {
+    success(parser(src).expression(0))
+}
+
+fn compound<'a>(lhs: Expression<'a>, op: LogicOp, rhs: Expression<'a>) -> Expression<'a> {
+    Expression::Compound(
+        Box::new(lhs),
+        Box::new(CompoundExpression::Logic(Logic::Atom(op, rhs))),
+    )
+}
+
+#[test]
+fn superclass_chain_test() {
+    let s = script("class A {} class B is A {} class C is B {} class D is Object {}");
+    assert_eq!(superclass_chain(&s, "C"), Ok(vec!["B", "A"]));
+    assert_eq!(superclass_chain(&s, "A"), Ok(vec![]));
+    assert_eq!(superclass_chain(&s, "D"), Ok(vec!["Object"]));
+    assert_eq!(superclass_chain(&s, "Missing"), Ok(vec![]));
+
+    let s = script("class A is C {} class B is A {} class C is B {}");
+    assert_eq!(superclass_chain(&s, "A"), Err(CycleError { class: "A" }));
+    let s = script("class A is A {}");
+    assert_eq!(superclass_chain(&s, "A"), Err(CycleError { class: "A" }));
+}
+
+#[test]
+fn negate_test() {
+    assert_eq!(
+        negate_expression(expression("a && b")),
+        compound(expression("!a"), LogicOp::Or, expression("!b"))
+    );
+    assert_eq!(
+        negate_expression(expression("a || b")),
+        compound(expression("!a"), LogicOp::And, expression("!b"))
+    );
+    assert_eq!(negate_expression(expression("a > b")), expression("a <= b"));
+    assert_eq!(negate_expression(expression("a <= b")), expression("a > b"));
+    assert_eq!(negate_expression(expression("a < b")), expression("a >= b"));
+    assert_eq!(negate_expression(expression("a >= b")), expression("a < b"));
+    assert_eq!(negate_expression(expression("a == b")), expression("a != b"));
+    assert_eq!(negate_expression(expression("a != b")), expression("a == b"));
+    assert_eq!(negate_expression(expression("!a")), expression("a"));
+    assert_eq!(negate_expression(expression("a")), expression("!a"));
+    assert_eq!(negate_expression(expression("a + b")), expression("!(a + b)"));
+    assert_eq!(negate_expression(expression("!!a")), expression("!!!a"));
+    assert_eq!(negate_expression(expression("!(a && b)")), expression("!!(a && b)"));
+    assert_eq!(
+        negate_expression(expression("a && b > c")),
+        compound(expression("!a"), LogicOp::Or, expression("b <= c"))
+    );
+
+    assert_eq!(
+        negate(Logic::Atom(LogicOp::Gt, expression("b"))),
+        Some(Logic::Atom(LogicOp::Le, expression("b")))
+    );
+    assert_eq!(
+        negate(Logic::Atom(LogicOp::And, expression("b"))),
+        Some(Logic::Atom(LogicOp::Or, expression("!b")))
+    );
+}
+
+#[test]
+fn negate_involution_test() {
+    let sources = [
+        "a && b",
+        "a || b && c",
+        "a > b",
+        "a == b || c != d",
+        "!a && !b",
+        "a + b",
+        "x is Num && y",
+        "a ? b : c",
+        "(a || b) && c",
+        "!(a > b)",
+        "!!a",
+        "!!!a",
+    ];
+    for src in sources {
+        let e = expression(src);
+        assert_eq!(negate_expression(negate_expression(e.clone())), e, "{}", src);
+    }
+    for op in [
+        LogicOp::Gt,
+        LogicOp::Lt,
+        LogicOp::Eq,
+        LogicOp::Le,
+        LogicOp::Ge,
+        LogicOp::NotEq,
+        LogicOp::Or,
+        LogicOp::And,
+    ] {
+        let l = Logic::Atom(op.clone(), expression("a && b"));
+        match negate(l.clone()) {
+            Some(n) => assert_eq!(negate(n), Some(l), "{:?}", op),
+            None => assert_eq!(op, LogicOp::Or),
+        }
+    }
+}
+
+#[test]
+fn negate_chained_logic_test() {
+    for (src, expect) in [
+        ("a == b || c < d", "a != b && c >= d"),
+        ("a > b && c", "a <= b || !c"),
+        ("a && b || c", "((!a) || !b) && !c"),
+        ("x > 0 && y < 1 || z", "(x <= 0 || y >= 1) && !z"),
+        ("a && b && c", "(!a) || (!b) || !c"),
+        ("(a || b) && c", "(!a) && (!b) || !c"),
+        ("(a || b) == c || d", "(a || b) != c && !d"),
+        ("a || b && c", "!(a || b && c)"),
+        ("a < b < c", "!(a < b < c)"),
+    ] {
+        let e = expression(src);
+        let negated = negate_expression(e.clone());
+        assert_eq!(negated.to_wren_source(), expect, "{}", src);
+        assert_eq!(negate_expression(negated), e, "{}", src);
+    }
+}
+
+#[test]
+fn variables_referenced_test() {
+    assert_eq!(
+        expression("a > 1 && b.size < c[i] || f(d)").variables_referenced(),
+        vec!["a", "b", "c", "i", "d"]
+    );
+    assert_eq!(expression("x + x * y").variables_referenced(), vec!["x", "y"]);
+    assert_eq!(expression("lo..hi").variables_referenced(), vec!["lo", "hi"]);
+    assert!(expression("1 + \"a\"").variables_referenced().is_empty());
+}
+
+#[test]
+fn condition_variables_test() {
+    let s = script(
+        r#"
+        if (a > 1 && b) {
+            x
+        } else if (c || a) {
+            y
+        } else {
+            z
+        }
+        "#,
+    );
+    let first = s.top_level_statements().next();
+    match first {
+        Some(Statement::If(i)) => assert_eq!(i.condition_variables(), vec!["a", "b", "c"]),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn doc_comment_test() {
+    let src = r#"
+// A binary tree.
+//   Nodes keep an item.
+class Tree {
+  construct new(item) { _item = item }
+}
+
+// Not attached: separated by a blank line.
+
+var tree = Tree.new(1)
+  // Attached, even when indented.
+  #leaf
+class Leaf is Tree {}
+"#;
+    let s = script(src);
+    assert_eq!(
+        extract_doc_comment(&s.units, 0, src),
+        Some("// A binary tree.\n//   Nodes keep an item.")
+    );
+    assert_eq!(extract_doc_comment(&s.units, 1, src), None);
+    assert_eq!(
+        extract_doc_comment(&s.units, 2, src),
+        Some("// Attached, even when indented.")
+    );
+    assert_eq!(extract_doc_comment(&s.units, 3, src), None);
+    assert_eq!(s.extract_doc_comments(src).iter().filter(|c| c.is_some()).count(), 2);
+
+    let other = script("class Tree {}");
+    assert_eq!(extract_doc_comment(&other.units, 0, src), None);
+}
+
+#[test]
+fn validate_class_def_test() {
+    let s = script("class A { construct new(a) {} \n name {} \n name(v) {} \n static name {} }");
+    assert_eq!(validate_class_def(s.get_class("A").unwrap()), vec![]);
+
+    let s = script(
+        "class A is A { construct new() {} \n check(a) {} \n check(b) {} \n static check(a) {} \n foreign static check(c) }",
+    );
+    assert_eq!(
+        validate_class_def(s.get_class("A").unwrap()),
+        vec![
+            SemanticError::SelfInheritance("A"),
+            SemanticError::DuplicateSignature {
+                signature: "check(_)".to_string(),
+                is_static: false
+            },
+            SemanticError::DuplicateSignature {
+                signature: "check(_)".to_string(),
+                is_static: true
+            },
+        ]
+    );
+
+    let mut s = script("class A { construct new() {} }");
+    if let Unit::Class(c) = &mut s.units[0] {
+        if let ClassStatement::Constructor(id, _, _) = &mut c.elems[0].statement {
+            id.value = "";
+        }
+    }
+    assert_eq!(
+        validate_class_def(s.get_class("A").unwrap()),
+        vec![SemanticError::AnonymousConstructor]
+    );
+}
+
+#[test]
+fn contains_call_to_test() {
+    let e = expression("a.b(f(x)) + list.map { |v| g(v) }[0]");
+    for name in ["a", "b", "f", "x", "list", "map", "g", "v"] {
+        assert!(e.contains_call_to(name), "{}", name);
+    }
+    assert!(!e.contains_call_to("h"));
+    assert!(!expression("1 + \"f\"").contains_call_to("f"));
+
+    let s = script("while (i < n) { i = next(i) }");
+    assert!(s.top_level_statements().any(|s| s.contains_call_to("next")));
+}
+
+#[test]
+fn is_effectful_test() {
+    let statement = |src| success(parser(src).statement(0));
+    assert!(!statement("1 + 2").is_effectful());
+    assert!(!statement("[1, \"a\", true] && !null").is_effectful());
+    assert!(statement("1 + x").is_effectful());
+    assert!(statement("System.print(1)").is_effectful());
+    assert!(statement("break").is_effectful());
+    assert!(statement("var x = 1").is_effectful());
+    assert!(statement("return 1").is_effectful());
+    assert!(statement("if (true) 1").is_effectful());
+    assert!(is_effectful(&statement("{ 1 }")));
+}
+
+#[test]
+fn useless_expressions_test() {
+    let s = script(
+        r#"
+        1 + 2
+        var a = 3
+        f(x) { return x }
+        class A {
+          value { 42 }
+          run() {
+            "unused"
+            a
+            return 1
+          }
+        }
+        { 4 * 5
+          [a] }
+        "#,
+    );
+    let printed: Vec<_> = useless_expressions(&s)
+        .into_iter()
+        .map(|e| e.to_wren_source())
+        .collect();
+    assert_eq!(printed, vec!["1 + 2", "\"unused\"", "4 * 5"]);
+}
+
+#[test]
+fn class_hierarchy_test() {
+    let s = script(
+        "class Leaf is Tree {} class Tree is Node {} class Node is Object {} \
+         class Branch is Tree {} class Other {} class X is Y {} class Y is X {}",
+    );
+    let hierarchy = class_hierarchy(&s);
+    assert_eq!(hierarchy.to_string(), "Node\n  Tree\n    Leaf\n    Branch\nOther\n");
+    assert_eq!(hierarchy.depth_of("Node"), 0);
+    assert_eq!(hierarchy.depth_of("Tree"), 1);
+    assert_eq!(hierarchy.depth_of("Leaf"), 2);
+    assert_eq!(hierarchy.depth_of("Missing"), 0);
+    assert_eq!(hierarchy.ancestors_of("Leaf"), vec!["Tree", "Node"]);
+    assert_eq!(hierarchy.ancestors_of("Branch"), vec!["Tree", "Node"]);
+    assert!(hierarchy.ancestors_of("Node").is_empty());
+    assert!(hierarchy.ancestors_of("X").is_empty());
+    assert_eq!(hierarchy.roots[0].children[0].class.name.value, "Tree");
+}
+
+#[test]
+fn complexity_test() {
+    let script = script(
+        r#"
+        f(xs) {
+          var n = 0
+          for (x in xs) {
+            if (x > 0) {
+              n = n + 1
+            } else if (x < 0) {
+              n = n - 1
+            }
+          }
+          while (n > 10) n = n - 1
+          return n
+        }
+        g()
+        "#,
+    );
+    let f = script.get_function("f").unwrap();
+    let metrics = complexity(f);
+    assert_eq!(
+        metrics,
+        ComplexityMetrics {
+            statements: 8,
+            branches: 4,
+            max_depth: 3,
+        }
+    );
+    assert_eq!(metrics.cyclomatic(), 5);
+
+    let body = f.block.as_ref().unwrap();
+    assert_eq!(body.count_statements(), 8);
+    assert_eq!(body.count_branches(), 4);
+    assert_eq!(body.max_depth(), 3);
+    assert_eq!(complexity(script.get_function("g").unwrap()), ComplexityMetrics::default());
+}
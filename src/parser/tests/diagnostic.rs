@@ -0,0 +1,104 @@
+use crate::parser::ast::Unit;
+use crate::parser::diagnostic::{ParseDiagnostic, SourceMap};
+use crate::parser::parser::CypherParser;
+use crate::parser::{ParseError, ParseErrorKind, SourceId};
+use std::path::Path;
+
+#[test]
+fn collect_diagnostics_test() {
+    let src = r#"
+        var a = 1
+        var = 2
+        class A {}
+        var b = )
+        var c = 3
+    "#;
+    let (script, errors) = CypherParser::new(src).unwrap().collect_diagnostics().script();
+    assert_eq!(script.units.len(), 4);
+    assert!(matches!(script.units[1], Unit::Class(_)));
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].location(src), (3, 9));
+    assert_eq!(errors[1].location(src), (5, 15));
+
+    let (script, errors) = CypherParser::new("var a = 1 class A {}")
+        .unwrap()
+        .collect_diagnostics()
+        .script();
+    assert_eq!(script.units.len(), 2);
+    assert!(errors.is_empty());
+
+    let src = "var a = 1 \n ) ) )";
+    let (script, errors) = CypherParser::new(src).unwrap().collect_diagnostics().script();
+    assert_eq!(script.units.len(), 1);
+    assert!(matches!(errors.as_slice(), [ParseError { kind: ParseErrorKind::FailedOnUnit(4), .. }]));
+    assert_eq!(
+        errors[0].to_string_with_context(src, 0).lines().next(),
+        Some("error: failed to parse unit at position 4")
+    );
+}
+
+#[test]
+fn parse_diagnostic_test() {
+    let src = "var a = 1\n\n  var b = )\nvar c = 3";
+    let (script, diagnostics) = CypherParser::new(src).unwrap().collect_diagnostics().diagnostics();
+    assert_eq!(script.units.len(), 3);
+    match diagnostics.as_slice() {
+        [d] => {
+            assert_eq!((d.line, d.column), (3, 9));
+            assert!(matches!(d.error.kind, ParseErrorKind::FailedOnUnit(6)));
+        }
+        other => panic!("{:?}", other),
+    }
+
+    let src = "a\nb\n  $";
+    let d = ParseDiagnostic::new(CypherParser::new(src).map(|_| ()).unwrap_err(), src);
+    assert_eq!((d.line, d.column), (3, 3));
+}
+
+#[test]
+fn source_id_test() {
+    let mut sources = SourceMap::new();
+    let main = sources.add("main.wren");
+    let lib = sources.add("lib/tree.wren");
+    assert_eq!(lib, SourceId(1));
+    assert_eq!(sources.path(main), Some(Path::new("main.wren")));
+    assert_eq!(sources.path(SourceId(2)), None);
+
+    let src = "var a = 1\nvar = 2";
+    let parser = CypherParser::with_source_id(src, lib).unwrap();
+    assert_eq!(parser.source_id(), Some(lib));
+    let (_, errors) = parser.collect_diagnostics().script();
+    match errors.as_slice() {
+        [e @ ParseError { kind: ParseErrorKind::FailedOnUnit(4), source_id: Some(SourceId(1)) }] => {
+            assert_eq!(sources.locate(e, src), Some((Path::new("lib/tree.wren"), 2, 1)));
+        }
+        other => panic!("{:?}", other),
+    }
+
+    let parser = CypherParser::with_source_id("1 )", main).unwrap();
+    match parser.parse_fragment(|pos| parser.id(pos)) {
+        Err(e) => assert_eq!(e.source_id, Some(main)),
+        other => panic!("{:?}", other),
+    }
+    match parser.parse(|pos| parser.expression(pos)) {
+        Err(ParseError { kind: ParseErrorKind::UnreachedEOF(1), source_id }) => {
+            assert_eq!(source_id, Some(main))
+        }
+        other => panic!("{:?}", other),
+    }
+    let parser = CypherParser::with_source_id("class A { f( }", lib).unwrap();
+    match parser.parse(|pos| parser.script(pos)) {
+        Err(e) => assert_eq!(e.source_id, Some(lib)),
+        other => panic!("{:?}", other),
+    }
+    match CypherParser::with_source_id("var $ = 1", main) {
+        Err(ParseError { kind: ParseErrorKind::BadToken(..), source_id }) => {
+            assert_eq!(source_id, Some(main))
+        }
+        other => panic!("{:?}", other.map(|_| ())),
+    }
+
+    let (_, errors) = CypherParser::new("var = 2").unwrap().collect_diagnostics().script();
+    assert_eq!(errors[0].source_id, None);
+    assert_eq!(sources.locate(&errors[0], "var = 2"), None);
+}
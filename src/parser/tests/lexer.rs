@@ -1,6 +1,8 @@
 use crate::parser::ast::Number::{Binary, Float, Hex, Int};
 use crate::parser::lexer::Token::*;
-use crate::parser::lexer::{CypherLexer, Token};
+use crate::parser::lexer::{CypherLexer, Token, TokenKind};
+use crate::parser::parser::CypherParser;
+use crate::parser::result::ParseResult;
 
 fn expect(src: &str, tokens: Vec<Token>) {
     match CypherLexer::new(src) {
@@ -69,3 +71,193 @@ fn words_test() {
 fn common_test() {
     expect_succeed(include_str!("parser/test_scripts/binary_tree.wren"))
 }
+
+#[test]
+fn from_tokens_test() {
+    let lexer = CypherLexer::from_tokens("", vec![Id("a"), Add, Digit(Int(1))]);
+    assert_eq!(lexer.len(), 3);
+    assert!(!lexer.is_empty());
+    assert!(CypherLexer::from_tokens("", vec![]).is_empty());
+    match CypherParser::from_lexer(lexer).expression(0) {
+        ParseResult::Success(_, pos) => assert_eq!(pos, 3),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn classification_test() {
+    let lexer = CypherLexer::new("if x + 1 == \"a\" { null } while").unwrap();
+    let kinds: Vec<_> = lexer
+        .tokens
+        .iter()
+        .map(|t| (t.is_keyword(), t.is_operator(), t.is_literal(), t.is_punctuation()))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (true, false, false, false),
+            (false, false, false, false),
+            (false, true, false, false),
+            (false, false, true, false),
+            (false, true, false, false),
+            (false, false, true, false),
+            (false, false, false, true),
+            (true, false, true, false),
+            (false, false, false, true),
+            (true, false, false, false),
+        ]
+    );
+    assert!(TextBlock("\"\"\"a\"\"\"").is_literal());
+    assert!(URShiftAssign.is_operator());
+    assert!(!Id("x").is_keyword());
+}
+
+#[test]
+fn kind_test() {
+    let kinds: Vec<TokenKind> = CypherLexer::new("var x = \"a\" + 0xff")
+        .unwrap()
+        .tokens
+        .iter()
+        .map(Token::kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            TokenKind::Var,
+            TokenKind::Id,
+            TokenKind::Assign,
+            TokenKind::StringLit,
+            TokenKind::Add,
+            TokenKind::Digit
+        ]
+    );
+    let operators = kinds.iter().filter(|k| k.is_operator()).count();
+    assert_eq!(operators, 2);
+    assert!(TokenKind::Digit.is_literal());
+    assert!(TokenKind::Hash.is_punctuation());
+    assert_eq!(Id("a").kind(), Id("b").kind());
+    assert_eq!(TokenKind::Hash.token(), Some(Token::Hash));
+    assert_eq!(TokenKind::Id.token(), None);
+    assert_eq!(TokenKind::Digit.token(), None);
+}
+
+#[test]
+fn keywords_test() {
+    let lexed: Vec<Token> = CypherLexer::keyword_strings()
+        .iter()
+        .flat_map(|k| CypherLexer::new(k).unwrap().tokens)
+        .collect();
+    assert_eq!(lexed, CypherLexer::keywords());
+    assert!(lexed.iter().all(Token::is_keyword));
+
+    let src = "== != && || ++ -- + - * / % & | ! ? > >= < <= ~ ^ = += -= *= &= |= ^= %= /= .. ... >> << >>= <<= >>>=";
+    assert_eq!(CypherLexer::new(src).unwrap().tokens, CypherLexer::operators());
+    assert!(CypherLexer::operators().iter().all(Token::is_operator));
+}
+
+#[test]
+fn token_at_byte_offset_test() {
+    let lexer = CypherLexer::new("var ab = 1 // c\n  x").unwrap();
+    assert_eq!(lexer.token_at_byte_offset(0), Some((&Var, 0)));
+    assert_eq!(lexer.token_at_byte_offset(2), Some((&Var, 0)));
+    assert_eq!(lexer.token_at_byte_offset(3), None);
+    assert_eq!(lexer.token_at_byte_offset(5), Some((&Id("ab"), 1)));
+    assert_eq!(lexer.token_at_byte_offset(7), Some((&Assign, 2)));
+    assert_eq!(lexer.token_at_byte_offset(9), Some((&Digit(Int(1)), 3)));
+    assert_eq!(lexer.token_at_byte_offset(13), None);
+    assert_eq!(lexer.token_at_byte_offset(18), Some((&Id("x"), 4)));
+    assert_eq!(lexer.token_at_byte_offset(19), None);
+
+    let lexer = CypherLexer::from_tokens("x", vec![Id("x")]);
+    assert_eq!(lexer.token_at_byte_offset(0), None);
+}
+
+#[test]
+fn source_slice_test() {
+    let src = "var x = foo(1,  2) // call\nx";
+    let lexer = CypherLexer::new(src).unwrap();
+    assert_eq!(lexer.source_slice(0, 1), Some("var"));
+    assert_eq!(lexer.source_slice(3, 9), Some("foo(1,  2)"));
+    assert_eq!(lexer.source_slice(0, 10), Some(src));
+    assert_eq!(lexer.source_slice(2, 2), None);
+    assert_eq!(lexer.source_slice(9, 11), None);
+    assert_eq!(CypherLexer::from_tokens(src, lexer.tokens.clone()).source_slice(0, 1), None);
+
+    let p = CypherParser::new(src).unwrap();
+    match p.expression(3) {
+        ParseResult::Success(_, end) => assert_eq!(p.source_slice(3, end), Some("foo(1,  2)")),
+        other => panic!("{:?}", other),
+    }
+}
+
+fn retokenized(src: &str, range: std::ops::Range<usize>, text: &str) {
+    let new_src = format!("{}{}{}", &src[..range.start], text, &src[range.end..]);
+    let lexer = CypherLexer::new(src).unwrap();
+    let expected = CypherLexer::new(&new_src).unwrap();
+    let actual = lexer.retokenize(range, &new_src).unwrap();
+    assert_eq!(actual.tokens, expected.tokens, "{}", new_src);
+    assert_eq!(actual.spans, expected.spans, "{}", new_src);
+}
+
+#[test]
+fn retokenize_test() {
+    let src = include_str!("parser/test_scripts/binary_tree.wren");
+    let start = src.find("Tree").unwrap();
+    let new_src = src.replacen("Tree", "Forest", 1);
+
+    let lexer = CypherLexer::new(src).unwrap();
+    let actual = lexer.retokenize(start..start + 4, &new_src).unwrap();
+    assert_eq!(actual.tokens, CypherLexer::new(&new_src).unwrap().tokens);
+    let changed: Vec<_> = lexer
+        .tokens
+        .iter()
+        .zip(actual.tokens.iter())
+        .enumerate()
+        .filter(|(_, (old, new))| old != new)
+        .map(|(i, _)| i)
+        .collect();
+    assert_eq!(changed, vec![2]);
+    assert_eq!(actual.tokens[2], Id("Forest"));
+
+    retokenized("var a = 1", 4..5, "abc");
+    retokenized("var a = 1", 4..5, "a b c");
+    retokenized("var a = 1", 4..5, "");
+    retokenized("var ab = 1", 6..6, "c");
+    retokenized("var a = 1", 9..9, " + 2");
+    retokenized("var a = 1", 0..0, "x ");
+    retokenized("x // note\ny", 9..9, " more");
+    retokenized("a \"b\" c", 3..3, "\" d \"");
+    retokenized("x = 'c' + \"s\" + 0x1f", 0..1, "yy");
+    retokenized("\"\"\"t\"\"\" 1.5 a", 8..8, "+ ");
+
+    let lexer = CypherLexer::new("var a = 1").unwrap();
+    assert!(lexer.retokenize(4..5, "var $ = 1").is_err());
+}
+
+#[test]
+fn tokens_iter_test() {
+    let src = "var x = \"a\" // c\nx";
+    let lexer = CypherLexer::new(src).unwrap();
+    let tokens: Vec<_> = lexer.tokens().map(|(t, span)| (*t, &src[span])).collect();
+    assert_eq!(
+        tokens,
+        vec![
+            (Token::Var, "var"),
+            (Token::Id("x"), "x"),
+            (Token::Assign, "="),
+            (Token::StringLit("\"a\""), "\"a\""),
+            (Token::Id("x"), "x"),
+        ]
+    );
+    assert_eq!(CypherLexer::from_tokens(src, lexer.tokens.clone()).tokens().count(), 0);
+}
+
+#[test]
+fn count_occurrences_test() {
+    let lexer = CypherLexer::new("import \"a\" for A \n import \"b\" \n var x = A.new(1) + 2").unwrap();
+    assert_eq!(lexer.count_keyword_occurrences(&Import), 2);
+    assert_eq!(lexer.count_keyword_occurrences(&Class), 0);
+    assert_eq!(lexer.count_occurrences(|t| matches!(t, StringLit(_))), 2);
+    assert_eq!(lexer.count_occurrences(Token::is_keyword), 4);
+    assert_eq!(lexer.count_occurrences(|_| true), lexer.len());
+}
@@ -1,2 +1,25 @@
+mod analysis;
+mod diagnostic;
 mod lexer;
 mod parser;
+mod printer;
+mod transform;
+mod util;
+
+use crate::parser::parser::CypherParser;
+use crate::parser::result::ParseResult;
+
+fn parser(src: &str) -> CypherParser<'_> {
+    match CypherParser::new(src) {
+        Ok(p) => p,
+        Err(e) => panic!("{:?}", e),
+    }
+}
+
+fn success<T>(res: ParseResult<T>) -> T {
+    match res {
+        ParseResult::Success(v, _) => v,
+        ParseResult::Fail(pos) => panic!("failed on {}", pos),
+        ParseResult::Error(e) => panic!("error: {:?}", e),
+    }
+}
@@ -0,0 +1,233 @@
+use crate::parser::ast::{Expression, ParseUnit, Statement};
+use crate::parser::lexer::Token;
+use crate::parser::parser::CypherParser;
+use crate::parser::result::ParseResult;
+use crate::parser::{
+    parse_classes, parse_expression_list, parse_functions, parse_non_empty_expression_list,
+    parse_script, parse_to_completion, ParseError, ParseErrorKind, SourceId,
+};
+
+#[test]
+fn expression_list_test() {
+    assert_eq!(parse_expression_list("a, b, c").map(|v| v.len()).ok(), Some(3));
+    assert_eq!(parse_expression_list("[1,2], x + 1").map(|v| v.len()).ok(), Some(2));
+    assert_eq!(parse_expression_list("").map(|v| v.len()).ok(), Some(0));
+    match parse_expression_list("a, b )") {
+        Err(ParseError { kind: ParseErrorKind::UnreachedEOF(3), .. }) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn non_empty_expression_list_test() {
+    assert_eq!(parse_non_empty_expression_list("a").map(|v| v.len()).ok(), Some(1));
+    match parse_non_empty_expression_list("") {
+        Err(ParseError { kind: ParseErrorKind::ReachedEOF(0), .. }) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn function_list_test() {
+    let src = r#"
+        new(item, depth) {
+            _item = item
+            if (depth > 0) {
+                var item2 = item + item
+                depth = depth - 1
+                _left = Tree.new(item2 - 1, depth)
+                _right = Tree.new(item2, depth)
+            }
+        },
+        check() {
+            if (_left == null) {
+                return _item
+            }
+            return _item + _left.check - _right.check
+        }
+    "#;
+    let names: Vec<_> = parse_functions(src)
+        .unwrap()
+        .iter()
+        .map(|f| f.name.value)
+        .collect();
+    assert_eq!(names, vec!["new", "check"]);
+    assert_eq!(parse_functions("").map(|v| v.len()).ok(), Some(0));
+    assert_eq!(parse_functions("a(), b(x), c(y, z) { return y }").map(|v| v.len()).ok(), Some(3));
+    match parse_functions("a(), b(") {
+        Err(ParseError { kind: ParseErrorKind::UnreachedEOF(3), .. }) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn class_list_test() {
+    let src = r#"
+        class Tree {
+            construct new(item) { _item = item }
+        }
+        #doc = "leaf"
+        class Leaf is Tree {
+            item { _item }
+        }
+    "#;
+    let names: Vec<_> = parse_classes(src)
+        .unwrap()
+        .iter()
+        .map(|c| (c.name.value, c.inherit.map(|i| i.value)))
+        .collect();
+    assert_eq!(names, vec![("Tree", None), ("Leaf", Some("Tree"))]);
+    assert_eq!(parse_classes("").map(|v| v.len()).ok(), Some(0));
+    match parse_classes("class A {} var a = 1") {
+        Err(ParseError { kind: ParseErrorKind::UnreachedEOF(4), .. }) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn error_location_test() {
+    let src = "a,\n  b,\n  c $ d";
+    match CypherParser::new(src) {
+        Err(e) => assert_eq!(e.location(src), (3, 5)),
+        Ok(_) => panic!("expected a bad token"),
+    }
+
+    let src = "a,\n  b,\n  c )";
+    match parse_expression_list(src) {
+        Err(e) => assert_eq!(e.location(src), (3, 5)),
+        other => panic!("{:?}", other),
+    }
+
+    let src = "a,\n  b,";
+    match parse_non_empty_expression_list(src) {
+        Err(e) => assert_eq!(e.location(src), (2, 4)),
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(ParseErrorKind::ReachedEOF(4).location(src), (2, 5));
+    assert_eq!(ParseErrorKind::FinishedOnFail(4).location(src), (2, 5));
+
+    let src = "a,\n  b,\n  1";
+    let parser = CypherParser::new(src).unwrap();
+    match parser.parse_fragment(|pos| parser.id(pos + 4)) {
+        Err(e) => assert_eq!(e.location(src), (3, 3)),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn parse_to_completion_test() {
+    match parse_to_completion("class A {}\nvar a = A.new()") {
+        Ok(ParseUnit::Script(s)) => assert_eq!(s.units.len(), 2),
+        other => panic!("{:?}", other),
+    }
+    match parse_to_completion("x > 1 && y") {
+        Ok(ParseUnit::Expression(Expression::Compound(..))) => {}
+        other => panic!("{:?}", other),
+    }
+    match parse_to_completion("while (x > 1) { x = x - 1 }") {
+        Ok(ParseUnit::Statement(Statement::While(..))) => {}
+        other => panic!("{:?}", other),
+    }
+    match parse_to_completion("class A { } )") {
+        Err(ParseError { kind: ParseErrorKind::UnreachedEOF(4), .. }) => {}
+        other => panic!("{:?}", other),
+    }
+    match parse_to_completion("") {
+        Err(ParseError { kind: ParseErrorKind::FinishedOnFail(0), .. }) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn parse_fragment_test() {
+    let parser = CypherParser::new("x + 1 }} rest").unwrap();
+    match parser.parse_fragment(|pos| parser.expression(pos)) {
+        Ok((_, 3)) => {}
+        other => panic!("{:?}", other),
+    }
+    match parser.validate_eof(parser.expression(0)) {
+        ParseResult::Error(ParseErrorKind::UnreachedEOF(3)) => {}
+        other => panic!("{:?}", other),
+    }
+
+    let parser = CypherParser::new("1").unwrap();
+    match parser.parse_fragment(|pos| parser.id(pos)) {
+        Err(ParseError { kind: ParseErrorKind::FinishedOnFail(0), .. }) => {}
+        other => panic!("{:?}", other),
+    }
+    match parser.parse_fragment(|pos| parser.id(pos + 1)) {
+        Err(ParseError { kind: ParseErrorKind::ReachedEOF(1), .. }) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn parse_or_default_test() {
+    let units = |src| CypherParser::parse_or_default(src, |p| p.script(0).map(|s| s.units.len()));
+    assert_eq!(units("var x = 1 \n class A {}"), 2);
+    assert_eq!(units("var x = 1 }"), 0);
+    assert_eq!(units("$"), 0);
+    assert_eq!(units(""), 0);
+
+    let ids = CypherParser::parse_or_default("a, b", |p| {
+        p.enumeration(0).map(|e| e.values.len())
+    });
+    assert_eq!(ids, 2);
+    assert_eq!(CypherParser::parse_or_default("1", |p| p.id(0).map(|id| id.value)), "");
+}
+
+#[test]
+fn to_string_with_context_test() {
+    let src = "var a = 1\n\nvar b = )\nvar c = 3\nvar d = 4";
+    let err = match parse_script(src) {
+        Err(e) => e,
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(
+        err.to_string_with_context(src, 1),
+        "error: expected end of input\n --> 3:7\n  |\n2 |\n3 | var b = )\n  |       ^\n4 | var c = 3\n"
+    );
+    assert_eq!(
+        err.to_string_with_context(src, 0),
+        "error: expected end of input\n --> 3:7\n  |\n3 | var b = )\n  |       ^\n"
+    );
+
+    let src = "var a = 1\nvar $b = 2";
+    let err = CypherParser::with_source_id(src, SourceId(0)).map(|_| ()).unwrap_err();
+    assert_eq!(
+        err.to_string_with_context(src, 5),
+        "error: unexpected token `$`\n --> 2:5\n  |\n1 | var a = 1\n2 | var $b = 2\n  |     ^\n"
+    );
+
+    let src = (1..=10).map(|i| format!("var a{} = {}", i, i)).collect::<Vec<_>>().join("\n");
+    let err = ParseErrorKind::FailedOnValidation("bad value", 35);
+    assert_eq!(
+        err.to_string_with_context(&src, 1),
+        "error: bad value\n  --> 9:10\n   |\n 8 | var a8 = 8\n 9 | var a9 = 9\n   |          ^\n10 | var a10 = 10\n"
+    );
+}
+
+#[test]
+fn position_of_test() {
+    let src = r#"
+        import "io" for File
+        var x = 1
+        #doc
+        foreign class A {}
+        f(a) { return a }
+        class B is A {}
+    "#;
+    let p = CypherParser::new(src).unwrap();
+    let script = match p.script(0) {
+        ParseResult::Success(s, _) => s,
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(p.position_of_import(&script, "io"), Some(0));
+    assert_eq!(p.position_of_import(&script, "\"io\""), Some(0));
+    assert_eq!(p.position_of_class(&script, "A"), Some(11));
+    assert_eq!(p.position_of_function(&script, "f"), Some(15));
+    assert_eq!(p.position_of_class(&script, "B"), Some(23));
+    assert_eq!(p.lookahead(23, 1), Some(&Token::Id("B")));
+    assert_eq!(p.position_of_class(&script, "C"), None);
+    assert_eq!(p.position_of_function(&script, "A"), None);
+}
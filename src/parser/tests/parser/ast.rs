@@ -0,0 +1,549 @@
+use crate::parser::ast::{
+    find_attribute_by_name, AtomExpression, AttributeList, Expression, Id, ImportModule, MergeError,
+    Number, Script, Unit,
+};
+use crate::parser::visitor::{walk, Node};
+use crate::parser::tests::{parser, success};
+
+#[test]
+fn attribute_test() {
+    let attr = success(parser("#!id = 1").attribute(0));
+    assert_eq!(attr.name().value, "id");
+    assert!(attr.is_negated());
+    assert_eq!(attr.values().len(), 1);
+
+    let attr = success(parser("#group(x = y, z)").attribute(0));
+    assert_eq!(attr.name().value, "group");
+    assert!(!attr.is_negated());
+    let ids: Vec<_> = attr.values().iter().map(|v| v.id.value).collect();
+    assert_eq!(ids, vec!["x", "z"]);
+}
+
+#[test]
+fn to_annotation_string_test() {
+    let cases = [
+        ("# id", "#id"),
+        ("#!id", "#!id"),
+        ("#!id = 1", "#!id = 1"),
+        ("#key = \"v\"", "#key = \"v\""),
+        ("#group( x=y ,z )", "#group(x = y, z)"),
+        ("#!group(x = [1, 2])", "#!group(x = [1, 2])"),
+    ];
+    for (src, expected) in cases {
+        let attr = success(parser(src).attribute(0));
+        let printed = attr.to_annotation_string();
+        assert_eq!(printed, expected);
+        assert_eq!(success(parser(&printed).attribute(0)), attr);
+    }
+}
+
+#[test]
+fn string_or_default_test() {
+    assert_eq!(success(parser("\"abc\"").atom(0)).string_or_default("-"), "abc");
+    assert_eq!(success(parser("'a'").atom(0)).string_or_default("-"), "a");
+    assert_eq!(success(parser("1").atom(0)).string_or_default("-"), "-");
+}
+
+#[test]
+fn param_names_test() {
+    let b = success(parser("{ |a, b| a + b }").block(0));
+    assert_eq!(b.param_names().collect::<Vec<_>>(), vec!["a", "b"]);
+    let f = success(parser("add(x, y) { return x + y }").function(0));
+    assert_eq!(f.param_names().collect::<Vec<_>>(), vec!["x", "y"]);
+    assert_eq!(f.params.names().count(), 2);
+    assert_eq!(success(parser("{ 1 }").block(0)).param_names().count(), 0);
+}
+
+#[test]
+fn atom_accessors_test() {
+    let atom = |src| success(parser(src).atom(0));
+    assert_eq!(atom("0x1f").as_number(), Some(Number::Hex(31)));
+    assert_eq!(atom("true").as_number(), None);
+    assert_eq!(atom("false").as_bool(), Some(false));
+    assert_eq!(atom("null").as_bool(), None);
+    assert_eq!(atom("\"abc\"").as_str(), Some("abc"));
+    assert_eq!(atom("'c'").as_str(), Some("c"));
+    assert_eq!(atom("abc").as_str(), None);
+    assert!(atom("null").is_null());
+    assert!(!atom("0").is_null());
+}
+
+#[test]
+fn id_visibility_test() {
+    let id = |src| success(parser(src).id(0));
+    assert!(id("_left").is_private());
+    assert!(!id("_left").is_class_level() && !id("_left").is_public());
+    assert!(id("__count").is_class_level());
+    assert!(!id("__count").is_private() && !id("__count").is_public());
+    assert!(id("tree").is_public() && id("Tree").is_public());
+    assert!(!id("tree").is_private() && !id("tree").is_class_level());
+}
+
+#[test]
+fn operator_precedence_test() {
+    let prec = |src| success(parser(src).compound_expr(0)).operator_precedence();
+    assert_eq!(prec("* 2"), 13);
+    assert_eq!(prec("+ 2"), 12);
+    assert_eq!(prec(".. 2"), 8);
+    assert_eq!(prec("<< 2"), 7);
+    assert_eq!(prec("& 2"), 6);
+    assert_eq!(prec("^ 2"), 5);
+    assert_eq!(prec("| 2"), 4);
+    assert_eq!(prec("> 2"), 3);
+    assert_eq!(prec("is Num"), 3);
+    assert_eq!(prec("&& b"), 2);
+    assert_eq!(prec("|| b"), 1);
+    assert_eq!(prec("? a : b"), 0);
+    assert_eq!(prec(".call()"), 15);
+    assert!(prec("* 2") > prec("+ 2"));
+
+    assert!(success(parser("+ 2").compound_expr(0)).is_left_associative());
+    assert!(!success(parser("? a : b").compound_expr(0)).is_left_associative());
+}
+
+#[test]
+fn arith_precedence_test() {
+    let arith = |src| success(parser(src).arith(0));
+    assert_eq!(arith("* 2").precedence(), 13);
+    assert_eq!(arith("- 2").precedence(), 12);
+    assert_eq!(arith("... 2").precedence(), 8);
+    assert_eq!(arith(">> 2").precedence(), 7);
+    assert_eq!(arith("| 2").precedence(), 4);
+    assert!(arith("& 2").precedence() > arith("^ 2").precedence());
+    assert!(arith("+ 2").associates_left());
+
+    let loosest = |src| success(parser(src).expression(0)).loosest_precedence();
+    assert_eq!(loosest("a * b + c"), 12);
+    assert_eq!(loosest("a == b || c"), 1);
+    assert_eq!(loosest("(a || b) * c"), 13);
+    assert_eq!(loosest("a.b"), 15);
+}
+
+#[test]
+fn depth_test() {
+    assert_eq!(success(parser("1").expression(0)).depth(), 1);
+    assert_eq!(success(parser("1 + 2").expression(0)).depth(), 2);
+    assert_eq!(success(parser("!(!x)").expression(0)).depth(), 3);
+    assert_eq!(success(parser("return 1").statement(0)).depth(), 2);
+    assert_eq!(success(parser("{ return 1 }").block(0)).depth(), 3);
+    assert_eq!(success(parser("{ f{ return 1 } }").block(0)).depth(), 6);
+}
+
+#[test]
+fn top_level_test() {
+    let script = success(parser("import \"a\" class A {} var x = 1 \n x + 1 \n 2").script(0));
+    assert_eq!(script.top_level_statements().count(), 3);
+    assert_eq!(script.top_level_expressions().count(), 2);
+    assert!(!script.is_library());
+
+    let script = success(parser("import \"a\" class A {}").script(0));
+    assert_eq!(script.top_level_statements().count(), 0);
+    assert!(script.is_library());
+}
+
+#[test]
+fn script_parts_test() {
+    let script = success(
+        parser("import \"a\" for A \n import \"b\" \n f(x) { return x } \n g() {} \n f(1)").script(0),
+    );
+    let imports: Vec<_> = script.imports().map(|i| i.name).collect();
+    assert_eq!(imports, vec!["\"a\"", "\"b\""]);
+    let functions: Vec<_> = script.functions().map(|f| f.name.value).collect();
+    assert_eq!(functions, vec!["f", "g"]);
+    assert_eq!(script.top_level_statements().count(), 1);
+    assert!(script.get_function("g").is_some());
+    assert!(script.get_function("f").is_some_and(|f| f.always_returns()));
+    assert!(script.get_function("h").is_none());
+}
+
+#[test]
+fn script_iter_test() {
+    let mut script = success(parser("import \"a\" class A {} var x = 1 \n class B {}").script(0));
+    assert_eq!((&script).into_iter().count(), 4);
+    for unit in &mut script {
+        if let Unit::Class(c) = unit {
+            c.name.value = "C";
+        }
+    }
+    let mut classes = 0;
+    for unit in &script {
+        if let Unit::Class(c) = unit {
+            assert_eq!(c.name.value, "C");
+            classes += 1;
+        }
+    }
+    assert_eq!(classes, 2);
+
+    let library: Script = script
+        .into_iter()
+        .filter(|u| !matches!(u, Unit::Statement(_)))
+        .collect();
+    assert_eq!(library.units.len(), 3);
+    assert!(library.is_library());
+}
+
+#[test]
+fn if_branches_test() {
+    let stmt = success(parser("if (a) x = 1 else if (b > 1) x = 2 else if (c) x = 3").if_statement(0));
+    assert_eq!(stmt.branch_count(), 3);
+    assert!(!stmt.has_else());
+    let conds: Vec<_> = stmt.branch_conditions().map(|c| c.to_wren_source()).collect();
+    assert_eq!(conds, vec!["a", "b > 1", "c"]);
+
+    let stmt = success(parser("if (a) return 1 else return 2").if_statement(0));
+    assert_eq!(stmt.branch_count(), 2);
+    assert!(stmt.has_else());
+    assert_eq!(stmt.branch_conditions().count(), 1);
+}
+
+#[test]
+fn always_returns_test() {
+    let returns = |src| success(parser(src).if_statement(0)).always_returns();
+    assert!(returns("if (a) return 1 else return 2"));
+    assert!(returns("if (a) { return 1 } else if (b) { x = 1 \n return 2 } else return 3"));
+    assert!(!returns("if (a) return 1 else if (b) x = 2 else return 3"));
+    assert!(!returns("if (a) return 1"));
+    assert!(returns("if (a) return 1 else if (b) return 2 else if (c) return 3 else { return 4 }"));
+    assert!(returns("if (a) if (b) return 1 else return 2 else return 3"));
+
+    let returns = |src| success(parser(src).function(0)).always_returns();
+    assert!(returns("f(a) { if (a) { return 1 } else { return 2 } }"));
+    assert!(returns("f(a) { if (a) { x = 1 } \n return 2 }"));
+    assert!(!returns("f(a) { if (a) { return 1 } }"));
+    assert!(!returns("f(a)"));
+}
+
+#[test]
+fn single_expression_test() {
+    let block = success(parser("{ a + 1 }").block(0));
+    assert!(block.is_single_expression());
+    assert_eq!(block.as_single_expression(), Some(&success(parser("a + 1").expression(0))));
+
+    assert!(!success(parser("{ |x| x + 1 }").block(0)).is_single_expression());
+    assert!(!success(parser("{ return 1 }").block(0)).is_single_expression());
+    assert!(!success(parser("{ a \n b }").block(0)).is_single_expression());
+    assert_eq!(success(parser("{}").block(0)).as_single_expression(), None);
+}
+
+#[test]
+fn placeholder_test() {
+    assert!(Expression::E.is_placeholder());
+    assert_eq!(Expression::E, Expression::E);
+
+    let sources = [
+        "1",
+        "!a && b || c",
+        "a ? b : c ? d : e",
+        "x is Num",
+        "[1, 2, {3: 4}][0]",
+        "a.b(1) { |x| x + 1 }.c",
+        "1..10",
+        "(a + b) * c - d / e % f",
+    ];
+    for src in sources {
+        let expr = success(parser(src).expression(0));
+        assert!(!expr.is_placeholder());
+        walk(Node::Expression(&expr), |n| {
+            if let Node::Expression(e) = n {
+                assert!(!e.is_placeholder(), "placeholder in {}", src);
+            }
+            true
+        });
+    }
+}
+
+#[test]
+fn chained_assignment_test() {
+    let rhs = |src| *success(parser(src).assignment(0)).rhs;
+
+    let plain = rhs("var a = 1");
+    assert!(!plain.is_chained());
+    assert_eq!(plain.depth(), 0);
+    assert!(plain.flatten_chain().is_empty());
+
+    let chained = rhs("var a = var b = var c = 1");
+    assert!(chained.is_chained());
+    assert_eq!(chained.depth(), 2);
+    let lhs: Vec<_> = chained
+        .flatten_chain()
+        .iter()
+        .map(|a| a.lhs.clone())
+        .collect();
+    assert_eq!(
+        lhs,
+        vec![
+            success(parser("b").expression(0)),
+            success(parser("c").expression(0))
+        ]
+    );
+}
+
+#[test]
+fn rhs_accessors_test() {
+    let rhs = |src| *success(parser(src).assignment(0)).rhs;
+
+    let plain = rhs("var a = 1");
+    assert_eq!(plain.as_expression(), Some(&success(parser("1").expression(0))));
+    assert!(plain.as_assignment().is_none());
+    assert_eq!(plain.into_expression(), Ok(success(parser("1").expression(0))));
+
+    let chained = rhs("var a = var b = 1");
+    assert!(chained.as_expression().is_none());
+    assert_eq!(
+        chained.as_assignment().map(|a| a.lhs.clone()),
+        Some(success(parser("b").expression(0)))
+    );
+    assert_eq!(chained.clone().into_expression(), Err(chained));
+}
+
+#[test]
+fn attribute_list_test() {
+    let class = success(parser("#key = 1 #group(a, b) #!key class A {}").class_def(0));
+    let attrs = &class.attributes;
+    assert_eq!(attrs.find("group").map(|a| a.values().len()), Some(2));
+    assert_eq!(attrs.find("key").map(|a| a.is_negated()), Some(false));
+    assert_eq!(attrs.find("missing"), None);
+    assert_eq!(attrs.all_named("key").count(), 2);
+    assert_eq!(attrs.as_slice().all_named("group").count(), 1);
+    assert_eq!(find_attribute_by_name(attrs, "group"), attrs.find("group"));
+}
+
+#[test]
+fn from_test() {
+    let expr: Expression = AtomExpression::from(Number::Int(1)).into();
+    assert_eq!(expr, success(parser("1").expression(0)));
+    let expr: Expression = AtomExpression::from(true).into();
+    assert_eq!(expr, success(parser("true").expression(0)));
+    let expr: Expression = AtomExpression::from("\"a\"").into();
+    assert_eq!(expr, success(parser("\"a\"").expression(0)));
+}
+
+#[test]
+fn block_function_conversion_test() {
+    let block = success(parser("{ |a, b| return a + b }").block(0));
+    let function = block.clone().to_function(Id { value: "sum" });
+    assert_eq!(function, success(parser("sum(a, b) { return a + b }").function(0)));
+    assert_eq!(function.to_block(), block);
+
+    let block = success(parser("{ x }").block(0));
+    assert_eq!(block.clone().to_function(Id { value: "f" }).to_block(), block);
+    assert_eq!(
+        success(parser("f(a)").function(0)).to_block(),
+        success(parser("{ |a| }").block(0))
+    );
+}
+
+#[test]
+fn to_closure_test() {
+    let function = success(parser("sum(a, b) { return a + b }").function(0));
+    let block = success(parser("{ |a, b| return a + b }").block(0));
+    assert_eq!(function.clone().to_closure(), block);
+
+    let closure = function.to_closure_with_receiver();
+    assert_eq!(closure.param_names().collect::<Vec<_>>(), vec!["this", "a", "b"]);
+    assert_eq!(closure.statements.len(), 1);
+    let closure = success(parser("f()").function(0)).to_closure_with_receiver();
+    assert_eq!(closure.param_names().collect::<Vec<_>>(), vec!["this"]);
+}
+
+#[test]
+fn checked_arith_test() {
+    use Number::*;
+    assert_eq!(Int(2).checked_add(&Int(3)), Some(Int(5)));
+    assert_eq!(Hex(0xf).checked_add(&Hex(1)), Some(Hex(0x10)));
+    assert_eq!(Hex(1).checked_add(&Int(1)), Some(Int(2)));
+    assert_eq!(Binary(2).checked_mul(&Binary(3)), Some(Binary(6)));
+    assert_eq!(Int(1).checked_add(&Float(0.5)), Some(Float(1.5)));
+    assert_eq!(Int(i64::MAX).checked_add(&Int(1)), None);
+    assert_eq!(Int(i64::MIN).checked_sub(&Hex(1)), None);
+    assert_eq!(Int(i64::MAX).checked_mul(&Int(2)), None);
+
+    assert_eq!(Int(6).checked_div(&Int(3)), Some(Int(2)));
+    assert_eq!(Int(7).checked_div(&Int(2)), Some(Float(3.5)));
+    assert_eq!(Int(1).checked_div(&Int(0)), None);
+    assert_eq!(Int(i64::MIN).checked_div(&Int(-1)), None);
+    assert_eq!(Float(1.0).checked_div(&Float(0.0)), Some(Float(f64::INFINITY)));
+    assert_eq!(Int(1).checked_div(&Float(0.0)), Some(Float(f64::INFINITY)));
+}
+
+#[test]
+fn to_wren_literal_test() {
+    assert_eq!(Number::Int(42).to_wren_literal(), Ok("42".to_string()));
+    assert_eq!(Number::Int(-7).to_wren_literal(), Ok("-7".to_string()));
+    assert_eq!(Number::Float(1.25).to_wren_literal(), Ok("1.25".to_string()));
+    assert_eq!(Number::Float(-2.5).to_wren_literal(), Ok("-2.5".to_string()));
+    assert_eq!(Number::Float(1.0).to_wren_literal(), Ok("1.0".to_string()));
+    assert_eq!(Number::Float(1e20).to_wren_literal(), Ok("1.0e20".to_string()));
+    assert_eq!(Number::Hex(255).to_wren_literal(), Ok("0xff".to_string()));
+    assert_eq!(Number::Hex(-255).to_wren_literal(), Ok("-0xff".to_string()));
+    assert_eq!(Number::Binary(13).to_wren_literal(), Ok("0b1101".to_string()));
+    assert!(Number::Binary(-1).to_wren_literal().is_err());
+    assert!(Number::Float(f64::INFINITY).to_wren_literal().is_err());
+    assert!(Number::Float(f64::NAN).to_wren_literal().is_err());
+
+    for n in [
+        Number::Int(42),
+        Number::Float(1.25),
+        Number::Float(-2.5),
+        Number::Float(1e20),
+        Number::Hex(255),
+        Number::Binary(13),
+    ] {
+        let literal = n.to_wren_literal().unwrap();
+        assert_eq!(success(parser(&literal).number(0)), n, "{}", literal);
+    }
+}
+
+#[test]
+fn dedup_literals_test() {
+    let dedup = |src| {
+        let mut e = success(parser(src).enumeration(0));
+        e.dedup_literals();
+        e
+    };
+    assert_eq!(dedup("1, 1, 2, 2, 3"), success(parser("1, 2, 3").enumeration(0)));
+    assert_eq!(dedup("a, a"), success(parser("a, a").enumeration(0)));
+    assert_eq!(
+        dedup("null, null, true, true, \"s\", \"s\", 'c', 'c', 1, true, 1"),
+        success(parser("null, true, \"s\", 'c', 1, true, 1").enumeration(0))
+    );
+    assert_eq!(dedup("a + 1, a + 1"), success(parser("a + 1, a + 1").enumeration(0)));
+}
+
+#[test]
+fn self_modifying_assignment_test() {
+    let assignment = |src| success(parser(src).assignment(0));
+
+    let a = assignment("x += 1");
+    assert!(a.is_self_modifying());
+    assert!(a.is_increment());
+    assert!(!a.is_decrement());
+    assert_eq!(a.modified_variable(), Some(&Id { value: "x" }));
+
+    let a = assignment("x += 2");
+    assert!(a.is_self_modifying());
+    assert!(!a.is_increment());
+
+    let a = assignment("var x = 1");
+    assert!(!a.is_self_modifying());
+    assert!(!a.is_increment());
+    assert_eq!(a.modified_variable(), Some(&Id { value: "x" }));
+
+    let a = assignment("a.b = 1");
+    assert_eq!(a.modified_variable(), None);
+
+    let a = assignment("x -= 1");
+    assert!(a.is_decrement());
+    assert!(!a.is_increment());
+}
+
+#[test]
+fn assign_op_test() {
+    let op = |src| success(parser(src).assignment(0)).op;
+    for (src, symbol, bitwise, arithmetic) in [
+        ("x = 1", "=", false, false),
+        ("x += 1", "+=", false, true),
+        ("x %= 1", "%=", false, true),
+        ("x &= 1", "&=", true, false),
+        ("x <<= 1", "<<=", true, false),
+        ("x >>>= 1", ">>>=", true, false),
+    ] {
+        let op = op(src);
+        assert_eq!(op.to_symbol(), symbol);
+        assert_eq!(op.is_bitwise(), bitwise, "{}", src);
+        assert_eq!(op.is_arithmetic(), arithmetic, "{}", src);
+        assert_eq!(op.is_simple(), !bitwise && !arithmetic, "{}", src);
+    }
+}
+
+#[test]
+fn range_test() {
+    let range = |src| success(parser(src).range(0));
+
+    let r = range("1..5");
+    assert_eq!(r.contains_num(1), Some(true));
+    assert_eq!(r.contains_num(5), Some(true));
+    assert_eq!(r.contains_num(6), Some(false));
+    assert_eq!(r.is_empty_range(), Some(false));
+
+    let r = range("1...5");
+    assert_eq!(r.contains_num(5), Some(false));
+    assert_eq!(r.contains_num(4), Some(true));
+
+    let r = range("5...1");
+    assert_eq!(r.contains_num(5), Some(true));
+    assert_eq!(r.contains_num(1), Some(false));
+
+    assert_eq!(range("3...3").is_empty_range(), Some(true));
+    assert_eq!(range("3...3").contains_num(3), Some(false));
+    assert_eq!(range("3..3").is_empty_range(), Some(false));
+
+    assert_eq!(range("1..5").overlaps(&range("5..9")), Some(true));
+    assert_eq!(range("1...5").overlaps(&range("5..9")), Some(false));
+    assert_eq!(range("9..5").overlaps(&range("1..5")), Some(true));
+    assert_eq!(range("1..2").overlaps(&range("3..4")), Some(false));
+    assert_eq!(range("3...3").overlaps(&range("1..9")), Some(false));
+
+    let r = range("0..a.count");
+    assert_eq!(r.contains_num(0), None);
+    assert_eq!(r.is_empty_range(), None);
+    assert_eq!(r.overlaps(&range("1..2")), None);
+}
+
+#[test]
+fn call_kind_test() {
+    let call = |src| success(parser(src).call(0));
+    let c = call("list.count");
+    assert!(c.is_field_access());
+    assert_eq!(c.callee_name(), "list");
+    let count = c.tail.as_ref().unwrap();
+    assert!(count.is_field_access() && !count.is_method_call());
+    assert_eq!(count.callee_name(), "count");
+
+    for src in ["f()", "f(1, 2)", "f { |x| x }"] {
+        let c = call(src);
+        assert!(c.is_method_call() && !c.is_field_access(), "{}", src);
+        assert_eq!(c.callee_name(), "f");
+    }
+    assert!(call("a.b(1)").tail.unwrap().is_method_call());
+}
+
+#[test]
+fn value_type_test() {
+    let class = |src| success(parser(src).class_def(0));
+    let num = class("foreign class Num { foreign abs() \n static pi { 3.14 } }");
+    assert!(num.is_likely_value_type());
+    assert!(!num.has_mutable_state());
+
+    assert!(!class("class Num { static pi { 3.14 } }").is_likely_value_type());
+    assert!(!class("foreign class Num { abs { 1 } }").is_likely_value_type());
+    assert!(!class("foreign class Num { foreign abs() \n static x { _x } }").is_likely_value_type());
+    assert!(!class("foreign class Num { foreign abs() \n static x { __x } }").is_likely_value_type());
+
+    let counter = class("class Counter { construct new() { _n = 0 } \n inc() { _n = _n + 1 } }");
+    assert!(counter.has_mutable_state());
+    assert!(class("class Cache { static reset() { __items = {} } }").has_mutable_state());
+    assert!(!class("class Pure { f(x) { var y = x \n return y } }").has_mutable_state());
+}
+
+#[test]
+fn merge_variables_test() {
+    let import = |src| success(parser(src).import_module(0));
+    let merged = ImportModule::merge_variables(
+        import("import \"m\" for A, B as C"),
+        import("import \"m\" for B, D"),
+    )
+    .unwrap();
+    assert_eq!(merged, import("import \"m\" for A, B as C, D"));
+    assert_eq!(
+        ImportModule::merge_variables(import("import \"m\""), import("import \"m\" for A")),
+        Ok(import("import \"m\" for A"))
+    );
+    assert_eq!(
+        ImportModule::merge_variables(import("import \"m\" for A"), import("import \"n\" for A")),
+        Err(MergeError {
+            left: "\"m\"",
+            right: "\"n\"",
+        })
+    );
+}
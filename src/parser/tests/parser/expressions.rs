@@ -1,4 +1,5 @@
-use crate::parser::tests::parser::{expect_pos, parser};
+use crate::parser::tests::parser::{expect_pos, fail_on_validation};
+use crate::parser::tests::{parser, success};
 
 
 #[test]
@@ -74,4 +75,70 @@ fn call_test() {
     expect_pos(parser("id{|a| a + 1 }.id").call(0), 11);
     expect_pos(parser("id{|a,b| [a,b] }.id").call(0), 15);
     expect_pos(parser("id{|a,b| {a:b} }.id().id").call(0), 19);
-}
\ No newline at end of file
+}
+#[test]
+fn max_expression_depth_test() {
+    expect_pos(parser("1 + 2").with_max_expression_depth(2).expression(0), 3);
+    fail_on_validation(
+        parser("1 + 2 * 3").with_max_expression_depth(2).expression(0),
+        "expression exceeds the maximum depth",
+    );
+    expect_pos(parser("1 + 2 * 3").with_max_expression_depth(3).expression(0), 5);
+    fail_on_validation(
+        parser("1 + 2 * 3").with_max_expression_depth(2).expression_with_precedence(0, 0),
+        "expression exceeds the maximum depth",
+    );
+
+    let deep = format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000));
+    fail_on_validation(
+        parser(&deep).with_max_expression_depth(64).expression(0),
+        "expression exceeds the maximum depth",
+    );
+}
+
+#[test]
+fn expression_with_precedence_test() {
+    let sources = [
+        "!x",
+        "!(1 + 2 * x)",
+        "(1 + 2 * x) + id",
+        "id + 1 || true && false",
+        "a * b + c",
+        "a - -b / 2 % c",
+        "x << 2 | y & 1 ^ z",
+        "a is Num && b == null",
+        "(a).b.c + 1",
+        "x ? y + 1 : z",
+        "f(a + b, [1, 2])[0] * {k: v}.count",
+        "(x)..10",
+        "a + ",
+        "a * b + c - d / e",
+        "a * (x + y) + c",
+        "a * !b + c",
+        "a > b && c > d && e || f",
+        "a is Num + 1 == b",
+        "a * b ? c : d",
+        "(a).b + 1",
+    ];
+    for src in sources {
+        let p = parser(src);
+        let (expected, climbed) = (p.expression(0), p.expression_with_precedence(0, 0));
+        assert_eq!(climbed.position(), expected.position(), "{}", src);
+        assert_eq!(success(climbed), success(expected), "{}", src);
+    }
+
+    let climb = |src, min| {
+        let p = parser(src);
+        let res = p.expression_with_precedence(0, min);
+        (res.position(), success(res).to_wren_source())
+    };
+    assert_eq!(climb("a * b + c", 13), (Some(3), "a * b".to_string()));
+    assert_eq!(climb("a + b * c", 13), (Some(1), "a".to_string()));
+    assert_eq!(climb("a + b * c", 12), (Some(5), "a + b * c".to_string()));
+    assert_eq!(climb("a > b && c", 3), (Some(3), "a > b".to_string()));
+    assert_eq!(climb("(a || b) * c", 13), (Some(7), "(a || b) * c".to_string()));
+    assert_eq!(climb("x ? 1 : 2", 1), (Some(1), "x".to_string()));
+    assert_eq!(climb("a * b + c * d || e", 12), (Some(7), "a * b + c * d".to_string()));
+    assert_eq!(climb("a && b == c || d", 2), (Some(5), "a && b == c".to_string()));
+    assert_eq!(climb("-a * b", 13), (Some(4), "-a * b".to_string()));
+}
@@ -3,18 +3,13 @@ mod expressions;
 mod units;
 mod statements;
 mod scripts;
+mod api;
+mod ast;
 
-use crate::parser::parser::CypherParser;
 use crate::parser::result::ParseResult;
+use crate::parser::ParseErrorKind;
 use std::fmt::Debug;
 
-fn parser(src: &str) -> CypherParser {
-    match CypherParser::new(src) {
-        Ok(p) => p,
-        Err(e) => panic!("{:?}", e),
-    }
-}
-
 fn expect<T>(res: ParseResult<T>, expect: T)
 where
     T: PartialEq + Debug,
@@ -58,3 +53,14 @@ fn fail_on<T: Debug>(res: ParseResult<T>, expect: usize) {
         ParseResult::Error(e) => panic!("error: {:?}", e),
     }
 }
+
+fn fail_on_validation<T: Debug>(res: ParseResult<T>, expect: &str) {
+    match res {
+        ParseResult::Error(ParseErrorKind::FailedOnValidation(mes, _)) => assert_eq!(mes, expect),
+        ParseResult::Success(v, pos) => {
+            panic!(" expect to get a validation error but got {:?} on pos {}", v, pos)
+        }
+        ParseResult::Fail(pos) => panic!("failed on {}", pos),
+        ParseResult::Error(e) => panic!("error: {:?}", e),
+    }
+}
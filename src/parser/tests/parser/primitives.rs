@@ -1,5 +1,11 @@
-use crate::parser::ast::AtomExpression;
-use crate::parser::tests::parser::{expect, expect_pos, fail, parser};
+use crate::parser::ast::{
+    AtomExpression, Call, CompoundExpression, Expression, Id, Logic, LogicOp,
+};
+use crate::parser::result::ParseResult;
+use crate::parser::lexer::Token;
+use crate::parser::ParseErrorKind;
+use crate::parser::tests::parser::{expect, expect_pos, fail, fail_on, fail_on_validation};
+use crate::parser::tests::{parser, success};
 
 #[test]
 fn enum_test() {
@@ -8,6 +14,29 @@ fn enum_test() {
     expect_pos(parser("a, [1,2]").enumeration(0), 7);
 }
 
+#[test]
+fn id_test() {
+    expect(parser("abc").id(0), Id { value: "abc" });
+    fail(parser("class").id(0));
+    fail(parser("while").id(0));
+}
+
+#[test]
+fn underscore_id_test() {
+    expect(parser("_").id(0), Id { value: "_" });
+    expect(parser("_field").id(0), Id { value: "_field" });
+    expect(parser("__static").id(0), Id { value: "__static" });
+    expect_pos(parser("_ = 1").assignment(0), 3);
+    expect_pos(parser("f { |_| 1 }").call(0), 7);
+}
+
+#[test]
+fn not_test() {
+    let p = parser("class A");
+    expect_pos(ParseResult::not(1, |pos| p.keyword(pos)), 1);
+    fail(ParseResult::not(0, |pos| p.keyword(pos)));
+}
+
 #[test]
 fn null_test() {
     expect(parser("null").null(0), AtomExpression::Null);
@@ -17,6 +46,26 @@ fn null_test() {
 #[test]
 fn elvis_test(){
     expect_pos(parser("? a : b").elvis(0), 4);
+    fail(parser("?: b").elvis(0));
+
+    let p = parser("x == null ? \"none\" : x.name");
+    match success(p.expression(0)) {
+        Expression::Compound(cond, tail) => match *tail {
+            CompoundExpression::Logic(Logic::Atom(LogicOp::Eq, rhs)) => {
+                assert_eq!(cond.to_wren_source(), "x");
+                assert_eq!(rhs.to_wren_source(), "null ? \"none\" : x.name");
+            }
+            other => panic!("{:?}", other),
+        },
+        other => panic!("{:?}", other),
+    }
+
+    let e = success(parser("ready ? a : b").expression(0));
+    match &e {
+        Expression::Compound(_, tail) => assert!(matches!(**tail, CompoundExpression::Elvis(_))),
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(e.to_wren_source(), "ready ? a : b");
 }
 
 #[test]
@@ -24,6 +73,12 @@ fn range_test() {
     expect_pos(parser("1..2").range(0), 3);
     expect_pos(parser("1...2").range(0), 3);
     expect_pos(parser("a.b.c...a{}").range(0), 9);
+    fail(parser("\"a\" + 1").range(0));
+
+    let mes = "range endpoints must be numeric or identifier";
+    fail_on_validation(parser("\"a\"..2").range(0), mes);
+    fail_on_validation(parser("1...'b'").range(0), mes);
+    fail_on_validation(parser("null..x").range(0), mes);
 }
 #[test]
 fn atom_test() {
@@ -31,6 +86,16 @@ fn atom_test() {
     expect_pos(parser("-a.b.c").atom(0), 6);
 }
 #[test]
+fn text_block_test() {
+    let src = "\"\"\"first line\n  second line\n\"\"\"";
+    expect(parser(src).atom(0), AtomExpression::TextBlock(src));
+    expect(parser("\"abc\"").atom(0), AtomExpression::StringLit("\"abc\""));
+    assert_eq!(
+        AtomExpression::TextBlock(src).string_or_default(""),
+        "first line\n  second line\n"
+    );
+}
+#[test]
 fn map_init_test() {
     expect_pos(parser("{}").map_init(0), 2);
     expect_pos(parser("{[1] : [2]}").map_init(0), 9);
@@ -43,3 +108,283 @@ fn list_init_test() {
     expect_pos(parser("[1]").list_init(0), 3);
     expect_pos(parser("[1 + 2 , b - a]").list_init(0), 9);
 }
+
+#[test]
+fn scan_test() {
+    let p = parser("a b { c { d } e } f");
+    expect(p.scan_forward(0, |t| *t == Token::RBrace), 6);
+    expect_pos(p.scan_forward(0, |t| *t == Token::RBrace), 7);
+    expect(p.scan_forward(2, |t| *t == Token::LBrace), 2);
+    expect(p.scan_to_matching_brace(2), 8);
+    expect_pos(p.scan_to_matching_brace(4), 7);
+    fail(p.scan_to_matching_brace(0));
+    match p.scan_forward(0, |t| *t == Token::Return) {
+        ParseResult::Error(ParseErrorKind::ReachedEOF(10)) => {}
+        other => panic!("{:?}", other),
+    }
+    match parser("{ a { b }").scan_to_matching_brace(0) {
+        ParseResult::Error(ParseErrorKind::ReachedEOF(_)) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn count_while_test() {
+    let p = parser("a b c 1");
+    expect(p.count_while(0, |pos| p.id(pos)), 3);
+    expect_pos(p.count_while(0, |pos| p.id(pos)), 3);
+    expect(p.count_while(3, |pos| p.id(pos)), 0);
+    expect(p.count_while(4, |pos| p.id(pos)), 0);
+
+    let p = parser("{ a = 1 \n b \n return c }");
+    expect(p.count_while(1, |pos| p.statement(pos)), 3);
+}
+
+#[test]
+fn tap_test() {
+    let p = parser("a b");
+    let mut seen = vec![];
+    let res = p
+        .id(0)
+        .tap(|r| seen.push(format!("{:?}", r)))
+        .then_zip(|pos| p.id(pos))
+        .tap(|r| seen.push(format!("{:?}", r)));
+    expect_pos(res, 2);
+    assert_eq!(seen.len(), 2);
+    assert!(seen[0].starts_with("Success(Id"));
+
+    let mut failed = None;
+    fail(p.number(0).tap(|r| {
+        if let ParseResult::Fail(pos) = r {
+            failed = Some(*pos)
+        }
+    }));
+    assert_eq!(failed, Some(0));
+}
+
+#[test]
+fn filter_test() {
+    let p = parser("a 1");
+    expect_pos(p.id(0).filter(|id| id.value == "a"), 1);
+    fail_on(p.id(0).filter(|id| id.value == "b"), 1);
+    fail_on(p.id(1).filter(|_| true), 1);
+    fail(p.one_or_more(1, |pos| p.id(pos)));
+    expect_pos(p.one_or_more(0, |pos| p.id(pos)), 1);
+    match p.id(2).filter(|_| true) {
+        ParseResult::Error(ParseErrorKind::ReachedEOF(2)) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn then_if_test() {
+    let is_new = |c: &Call| c.tail.as_ref().is_some_and(|t| t.id.value == "new");
+    let p = parser("Tree.new(1) 2");
+    match p.call(0).then_if(is_new).then_zip(|pos| p.number(pos)) {
+        ParseResult::Success((c, _), 7) => assert_eq!(c.id.value, "Tree"),
+        other => panic!("{:?}", other),
+    }
+
+    let p = parser("tree.depth(1) 2");
+    fail_on(p.call(0).then_if(is_new).then(|pos| p.number(pos)), 6);
+}
+
+#[test]
+fn recover_with_test() {
+    let p = parser("a ) b");
+    let whole = p.validate_eof(p.id(0));
+    match whole.recover_with(Id { value: "_" }, 1).then_zip(|pos| p.id(pos)) {
+        ParseResult::Success((Id { value: "_" }, Id { value: "b" }), 3) => {}
+        other => panic!("{:?}", other),
+    }
+    fail_on(p.id(1).recover_with(Id { value: "_" }, 1), 1);
+    expect_pos(p.id(1).or_val(Id { value: "_" }), 1);
+    match p.id(3).recover_with(Id { value: "_" }, 1) {
+        ParseResult::Error(ParseErrorKind::ReachedEOF(3)) => {}
+        other => panic!("{:?}", other),
+    }
+
+    let bad = |_| ParseResult::Error(ParseErrorKind::BadToken("$", 2..3));
+    match bad(0).recover_with(Id { value: "_" }, 2).then_zip(|pos| p.id(pos)) {
+        ParseResult::Success((Id { value: "_" }, Id { value: "b" }), 3) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn any_token_test() {
+    let p = parser("class + x");
+    expect(p.any_token(0), &Token::Class);
+    expect(p.any_token(1), &Token::Add);
+    expect_pos(p.any_token(2), 3);
+    match p.any_token(3) {
+        ParseResult::Error(ParseErrorKind::ReachedEOF(3)) => {}
+        other => panic!("{:?}", other),
+    }
+
+    expect(p.any_token_except(1, |t| t.is_keyword()), &Token::Add);
+    fail_on(p.any_token_except(0, |t| t.is_keyword()), 0);
+    let skipped = p.count_while(0, |pos| p.any_token_except(pos, |t| matches!(t, Token::Id(_))));
+    expect(skipped, 2);
+}
+
+#[test]
+fn success_accessors_test() {
+    let p = parser("a 1");
+    assert!(p.id(0).is_success() && !p.id(0).is_fail() && !p.id(0).is_error());
+    assert!(p.id(1).is_fail());
+    assert!(p.id(2).is_error());
+    assert_eq!(p.id(0).success_or_else(|| Id { value: "_" }).value, "a");
+    assert_eq!(p.id(1).success_or_else(|| Id { value: "_" }).value, "_");
+    assert_eq!(p.one_or_more(1, |pos| p.id(pos)).success_or_default(), vec![]);
+    assert_eq!(p.id(0).error_message(), None);
+    assert_eq!(p.id(1).error_message(), None);
+    assert_eq!(p.id(2).error_message(), Some("unexpected end of input".to_string()));
+}
+
+#[test]
+fn seek_test() {
+    let p = parser("var x = a + 1");
+    expect(p.seek(3), 3);
+    expect_pos(p.seek(3).then(|pos| p.expression(pos)), 6);
+    fail_on(p.seek(4).then(|pos| p.id(pos)), 4);
+    match p.seek(6).then(|pos| p.expression(pos)) {
+        ParseResult::Error(ParseErrorKind::ReachedEOF(6)) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn try_all_test() {
+    let p = parser("a.b + 1");
+    let id = |pos| p.id(pos).map(|_| "id");
+    let call = |pos| p.call(pos).map(|_| "call");
+    let number = |pos| p.number(pos).map(|_| "number");
+    let expr = |pos| p.expression(pos).map(|_| "expression");
+
+    let results: Vec<_> = p
+        .try_all(0, &[&id, &call, &number, &expr])
+        .into_iter()
+        .map(|r| match r {
+            ParseResult::Success(v, pos) => (v, pos),
+            other => panic!("{:?}", other),
+        })
+        .collect();
+    assert_eq!(results, vec![("id", 1), ("call", 3), ("expression", 5)]);
+    assert!(p.try_all(0, &[&number]).is_empty());
+    assert!(p.try_all(10, &[&id, &number]).is_empty());
+}
+
+#[test]
+fn string_or_id_test() {
+    expect(parser("\"abc\"").string_or_id(0), "\"abc\"");
+    expect(parser("abc").string_or_id(0), "abc");
+    fail(parser("class").string_or_id(0));
+    fail(parser("1").string_or_id(0));
+}
+
+#[test]
+fn qualified_id_test() {
+    let ids = |values: &[&'static str]| values.iter().map(|&value| Id { value }).collect::<Vec<_>>();
+    expect(parser("Foo.Bar.Baz").qualified_id(0), ids(&["Foo", "Bar", "Baz"]));
+    expect(parser("Foo").qualified_id(0), ids(&["Foo"]));
+    expect_pos(parser("Foo.Bar.").qualified_id(0), 3);
+    expect_pos(parser("Foo.Bar(1)").qualified_id(0), 3);
+    fail(parser("1.Foo").qualified_id(0));
+}
+
+#[test]
+fn lookahead_test() {
+    let p = parser("a [ 1");
+    assert_eq!(p.lookahead(0, 1), Some(&Token::LBrack));
+    assert!(matches!(p.lookahead(1, 1), Some(Token::Digit(_))));
+    assert_eq!(p.lookahead(0, 3), None);
+
+    match parser("x[0]").atom(0) {
+        ParseResult::Success(AtomExpression::CollectionElem(..), 4) => {}
+        other => panic!("{:?}", other),
+    }
+    match parser("a.b[1]").atom(0) {
+        ParseResult::Success(AtomExpression::CollectionElem(..), 6) => {}
+        other => panic!("{:?}", other),
+    }
+    match parser("x").atom(0) {
+        ParseResult::Success(AtomExpression::Call(_), 1) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn contextualize_error_test() {
+    let bad = ParseResult::<()>::Error(ParseErrorKind::FailedOnValidation("bad", 3));
+    match bad.contextualize_error("call") {
+        ParseResult::Error(ParseErrorKind::Contextualised { context: "call", inner }) => {
+            assert!(matches!(*inner, ParseErrorKind::FailedOnValidation("bad", 3)))
+        }
+        other => panic!("{:?}", other),
+    }
+    match ParseResult::<()>::Error(ParseErrorKind::ReachedEOF(2)).contextualize_error("call") {
+        ParseResult::Error(ParseErrorKind::ReachedEOF(2)) => {}
+        other => panic!("{:?}", other),
+    }
+    expect(ParseResult::Success(1, 1).contextualize_error("call"), 1);
+    fail_on(ParseResult::<()>::Fail(4).contextualize_error("call"), 4);
+    expect(
+        ParseResult::Error(ParseErrorKind::FinishedOnFail(0)).flat_map_error(|_| ParseResult::Success(0, 0)),
+        0,
+    );
+
+    match parser("if (\"a\"..2) x").if_statement(0) {
+        ParseResult::Error(ParseErrorKind::Contextualised { context: "if statement", inner }) => {
+            assert!(matches!(*inner, ParseErrorKind::FailedOnValidation(..)))
+        }
+        other => panic!("{:?}", other),
+    }
+
+    let src = "#key = \"a\"..2\nclass A {}";
+    let err = match parser(src).class_def(0) {
+        ParseResult::Error(e) => e,
+        other => panic!("{:?}", other),
+    };
+    assert!(matches!(err, ParseErrorKind::Contextualised { context: "class definition", .. }));
+    assert_eq!(
+        err.to_string_with_context(src, 0).lines().next(),
+        Some("error: range endpoints must be numeric or identifier in class definition")
+    );
+}
+
+#[test]
+fn peek_keyword_test() {
+    let p = parser("while (x) x = x - 1");
+    assert!(p.peek_keyword(0, &Token::While));
+    assert!(!p.peek_keyword(0, &Token::If));
+    assert!(!p.peek_keyword(1, &Token::While));
+    assert!(!p.peek_keyword(100, &Token::While));
+    expect_pos(p.statement(0), 9);
+    fail_on(parser("for x").statement(0), 1);
+}
+
+#[test]
+fn position_test() {
+    let p = parser("a + 1 )");
+    assert_eq!(p.expression(0).position(), Some(3));
+    assert_eq!(p.id(1).position(), Some(1));
+    assert_eq!(p.id(10).position(), None);
+    assert!(matches!(p.expression(0).end_position(), Ok(3)));
+    assert!(matches!(p.id(10).end_position(), Err(ParseErrorKind::ReachedEOF(10))));
+}
+
+#[test]
+fn and_test() {
+    let p = parser("a.b + 1");
+    let (call, expr) = success(p.call(0).and(p.expression(0)));
+    assert_eq!(call.id.value, "a");
+    assert_eq!(expr.to_wren_source(), "a.b + 1");
+    expect_pos(p.call(0).and(p.expression(0)), 5);
+    fail_on(p.number(0).and(p.id(2)), 3);
+    fail_on(p.id(0).and(p.number(0)), 1);
+    match p.id(0).and(p.id(10)) {
+        ParseResult::Error(ParseErrorKind::ReachedEOF(10)) => {}
+        other => panic!("{:?}", other),
+    }
+}
@@ -1,7 +1,136 @@
-use crate::parser::tests::parser::{expect_pos, parser};
+use crate::parser::analysis::{to_dot, walk_calls, ScriptMetrics};
+use crate::parser::ast::{ClassBodyType, ClassStatement};
+use crate::parser::tests::parser::expect_pos;
+use crate::parser::tests::{parser, success};
+use crate::parser::visitor::{depth, Node};
 
 #[test]
 fn script(){
     let script: &str = include_str!("test_scripts/binary_tree.wren");
     expect_pos(parser(script).script(0).debug(),219)
-}
\ No newline at end of file
+}
+#[test]
+fn binary_tree_check_test() {
+    let script = success(parser(include_str!("test_scripts/binary_tree.wren")).script(0));
+    let check = script
+        .get_class("Tree")
+        .and_then(|c| c.elems.iter().find(|u| u.statement.signature() == "check"))
+        .map(|u| &u.statement);
+    match check {
+        Some(ClassStatement::OpGetter(_, Some(block))) => {
+            assert!(block.statements.iter().any(|s| s.contains_call_to("_left")));
+            assert!(block.statements.iter().any(|s| s.contains_call_to("check")));
+            assert!(!block.statements.iter().any(|s| s.contains_call_to("new")));
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn binary_tree_signatures_test() {
+    let script = success(parser(include_str!("test_scripts/binary_tree.wren")).script(0));
+    let tree = script.get_class("Tree").unwrap();
+    let signatures: Vec<_> = tree.all_method_signatures().collect();
+    assert_eq!(
+        signatures,
+        vec![
+            (ClassBodyType::None, "new(_,_)".to_string()),
+            (ClassBodyType::None, "check".to_string())
+        ]
+    );
+    assert_eq!(tree.all_method_signatures_in(&script), signatures);
+}
+
+#[test]
+fn inherited_signatures_test() {
+    let script = success(
+        parser(
+            r#"
+            class Base {
+              construct new() {}
+              static create() { return Base.new() }
+              check { return 1 }
+              size(a) { return a }
+            }
+            class Tree is Base {
+              construct new(x) {}
+              check { return 2 }
+              left() { return _left }
+            }
+            class Leaf is Tree {
+              static leaf { return 1 }
+            }
+            "#,
+        )
+        .script(0),
+    );
+    let signatures = |name| -> Vec<String> {
+        script
+            .get_class(name)
+            .unwrap()
+            .all_method_signatures_in(&script)
+            .into_iter()
+            .map(|(_, s)| s)
+            .collect()
+    };
+    assert_eq!(signatures("Base"), vec!["new()", "create()", "check", "size(_)"]);
+    assert_eq!(signatures("Tree"), vec!["new(_)", "check", "left()", "size(_)"]);
+    assert_eq!(signatures("Leaf"), vec!["leaf", "check", "left()", "size(_)"]);
+
+    let cyclic = success(parser("class A is B { a() {} } class B is A { b() {} }").script(0));
+    let a = cyclic.get_class("A").unwrap();
+    assert_eq!(a.all_method_signatures_in(&cyclic).len(), 2);
+}
+
+#[test]
+fn binary_tree_metrics_test() {
+    let script = success(parser(include_str!("test_scripts/binary_tree.wren")).script(0));
+    let metrics = script.size();
+    assert_eq!(
+        metrics,
+        ScriptMetrics {
+            total_classes: 1,
+            total_methods: 1,
+            total_constructors: 1,
+            total_statements: 28,
+            total_expressions: 89,
+            max_nesting_depth: 9,
+        }
+    );
+    assert_eq!(metrics.max_nesting_depth, depth(Node::Script(&script)));
+
+    let metrics = success(parser("class A { construct new() {} a {} b() {} } \n 1 + 2").script(0)).size();
+    assert_eq!((metrics.total_methods, metrics.total_constructors), (2, 1));
+    assert_eq!((metrics.total_statements, metrics.total_expressions), (1, 3));
+}
+
+#[test]
+fn binary_tree_calls_test() {
+    let script = success(parser(include_str!("test_scripts/binary_tree.wren")).script(0));
+    let tree_new = script
+        .walk_calls()
+        .filter(|c| c.id.value == "Tree" && c.tail.as_ref().is_some_and(|t| t.id.value == "new"))
+        .count();
+    assert!(tree_new >= 3, "{}", tree_new);
+    assert!(script.walk_calls().any(|c| c.id.value == "check"));
+    assert!(script.walk_calls().any(|c| c.id.value == "print"));
+    assert_eq!(walk_calls(&script).count(), script.walk_calls().count());
+}
+
+#[test]
+fn to_dot_test() {
+    let script = success(parser(include_str!("test_scripts/binary_tree.wren")).script(0));
+    assert_eq!(
+        script.to_dot(),
+        "digraph classes {\n    node [shape=box];\n    \"Tree\" [shape=ellipse];\n}\n"
+    );
+
+    let src = "class A {} \n class B is A {} \n class C is B {} \n class D is Object {}";
+    let script = success(parser(src).script(0));
+    let dot = to_dot(&script);
+    assert!(dot.contains("    \"A\";\n"));
+    assert!(dot.contains("    \"A\" -> \"B\";\n"));
+    assert!(dot.contains("    \"B\" -> \"C\";\n"));
+    assert!(dot.contains("    \"Object\" -> \"D\";\n"));
+    assert_eq!(dot.matches("->").count(), 3);
+}
@@ -1,4 +1,6 @@
-use crate::parser::tests::parser::{expect_pos, parser};
+use crate::parser::ast::{AssignOp, BlockOrStatement, Number, RangeExpression};
+use crate::parser::tests::parser::expect_pos;
+use crate::parser::tests::{parser, success};
 
 
 #[test]
@@ -15,6 +17,55 @@ fn if_test() {
     );
     expect_pos(parser("if(a > b || b > c && !z) {a} else { c }").if_statement(0), 20);
 }
+#[test]
+fn block_or_single_test() {
+    let is_block = |b: &BlockOrStatement| matches!(b, BlockOrStatement::Block(_));
+
+    let i = success(parser("if(a) { b } else c").if_statement(0));
+    assert!(is_block(&i.main.action));
+    assert!(!is_block(i.els.as_ref().unwrap()));
+
+    let w = success(parser("while(a) b = c").while_statement(0));
+    assert!(!is_block(&w.body));
+
+    let f = success(parser("for(x in xs) { x }").for_statement(0));
+    assert!(is_block(&f.body));
+    expect_pos(parser("{ a } b").block_or_single(0), 3);
+}
+#[test]
+fn for_kind_test() {
+    let f = success(parser("for(i in 0...depth) {}").for_statement(0));
+    assert!(f.is_range_for() && !f.is_collection_for());
+    match f.range_bounds() {
+        Some((RangeExpression::Num(Number::Int(0)), RangeExpression::Call(c), true)) => {
+            assert_eq!(c.id.value, "depth")
+        }
+        other => panic!("{:?}", other),
+    }
+    let f = success(parser("for(i in 1..3) {}").for_statement(0));
+    assert!(matches!(f.range_bounds(), Some((_, _, false))));
+
+    for src in ["for(x in xs) {}", "for(x in [1, 2]) {}", "for(x in (1..3).toList) {}"] {
+        let f = success(parser(src).for_statement(0));
+        assert!(f.is_collection_for() && f.range_bounds().is_none(), "{}", src);
+    }
+}
+
+#[test]
+fn while_cond_test() {
+    let w = success(parser("while(a < 10) a = a + 1").while_statement(0));
+    assert!(w.cond.as_assignment().is_none());
+    assert_eq!(w.cond.as_expression(), w.cond.condition_expression());
+
+    let w = success(parser("while(var line = reader.next) {}").while_statement(0));
+    assert!(w.cond.as_expression().is_none());
+    assert!(w.cond.as_assignment().is_some());
+    assert_eq!(
+        w.cond.condition_expression().map(|e| e.to_wren_source()),
+        Some("reader.next".to_string())
+    );
+}
+
 #[test]
 fn assignment_test() {
     expect_pos(parser("a = b").assignment(0), 3);
@@ -22,6 +73,23 @@ fn assignment_test() {
     expect_pos(parser("var x = var y = 1").assignment(0), 7);
 }
 #[test]
+fn assignment_op_test() {
+    let op = |src| success(parser(src).assignment(0)).op;
+    assert_eq!(op("x = 1"), AssignOp::Assign);
+    assert_eq!(op("x += 1"), AssignOp::Add);
+    assert_eq!(op("x -= 1"), AssignOp::Sub);
+    assert_eq!(op("x *= 2"), AssignOp::Mul);
+    assert_eq!(op("x /= 2"), AssignOp::Div);
+    assert_eq!(op("x &= y"), AssignOp::And);
+    assert_eq!(op("x |= y"), AssignOp::Or);
+    assert_eq!(op("x ^= y"), AssignOp::Xor);
+    assert_eq!(op("x %= 2"), AssignOp::Mod);
+    assert_eq!(op("x <<= 1"), AssignOp::LShift);
+    assert_eq!(op("x >>= 1"), AssignOp::RShift);
+    assert_eq!(op("x >>>= 1"), AssignOp::URShift);
+    expect_pos(parser("x << 1").expression(0), 3);
+}
+#[test]
 fn assignment_null_test() {
     expect_pos(parser("var a").assignment_null(0), 2);
 }
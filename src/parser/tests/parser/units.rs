@@ -1,4 +1,6 @@
-use crate::parser::tests::parser::{expect_pos, fail_on, parser};
+use crate::parser::ast::{ClassBodyType, ClassStatement, Id};
+use crate::parser::tests::parser::{expect_pos, fail_on};
+use crate::parser::tests::{parser, success};
 
 #[test]
 fn import_mod_test() {
@@ -69,6 +71,15 @@ fn class_body_test() {
     );
 }
 #[test]
+fn class_body_type_test() {
+    let tpe = |src| success(parser(src).class_body(0)).tpe;
+    assert_eq!(tpe("foreign static x()"), ClassBodyType::ForeignStatic);
+    assert_eq!(tpe("static foreign x()"), ClassBodyType::ForeignStatic);
+    assert_eq!(tpe("static x()"), ClassBodyType::Static);
+    assert_eq!(tpe("foreign x()"), ClassBodyType::Foreign);
+    assert_eq!(tpe("x()"), ClassBodyType::None);
+}
+#[test]
 fn class_unit_test() {
     expect_pos(
         parser(r#"
@@ -93,3 +104,70 @@ fn class_unit_test() {
     );
 }
 
+#[test]
+fn abstract_methods_test() {
+    let class = success(
+        parser(
+            r#"
+        foreign class Tree {
+          construct new(item, depth) {
+            _item = item
+          }
+          foreign hash()
+          foreign static create(item)
+          static depth()
+          foreign check {
+            return _item
+          }
+        }
+        "#,
+        )
+        .class_def(0),
+    );
+    let abstracts: Vec<_> = class.elems.iter().map(|u| u.is_abstract()).collect();
+    assert_eq!(abstracts, vec![false, true, true, false, false]);
+    let names: Vec<_> = class.abstract_methods().map(|f| f.name.value).collect();
+    assert_eq!(names, vec!["hash", "create"]);
+}
+
+
+#[test]
+fn class_body_list_test() {
+    expect_pos(parser("{}").class_body_list(0), 2);
+    expect_pos(parser("{ x() y { return 1 } }").class_body_list(0), 10);
+
+    let script: &str = include_str!("test_scripts/binary_tree.wren");
+    let p = parser(script);
+    let class = success(p.class_def(0));
+    assert_eq!(class.name.value, "Tree");
+    assert_eq!(class.elems.len(), 2);
+    assert_eq!(success(p.class_body_list(3)), class.elems);
+}
+
+#[test]
+fn signature_test() {
+    let signature = |src| success(parser(src).class_statement(0)).signature();
+    assert_eq!(signature("construct new(item, depth) {}"), "new(_,_)");
+    assert_eq!(signature("construct create() {}"), "create()");
+    assert_eq!(signature("check { return 1 }"), "check");
+    assert_eq!(signature("sum(a, b, c) { return a }"), "sum(_,_,_)");
+    assert_eq!(signature("clear() {}"), "clear()");
+    assert_eq!(signature("+(other) { return other }"), "+(_)");
+    assert_eq!(signature("==(other) { return true }"), "==(_)");
+    assert_eq!(signature("- { return 0 }"), "-");
+    assert_eq!(signature("! { return false }"), "!");
+
+    let id = |value| Id { value };
+    let block = || success(parser("{}").block(0));
+    let args = |src| success(parser(src).enumeration(0));
+    assert_eq!(
+        ClassStatement::Setter(id("name"), id("value"), block()).signature(),
+        "name=(_)"
+    );
+    assert_eq!(ClassStatement::SubscriptGet(args("i"), block()).signature(), "[_]");
+    assert_eq!(ClassStatement::SubscriptGet(args("x, y"), block()).signature(), "[_,_]");
+    assert_eq!(
+        ClassStatement::SubscriptSet(args("x, y"), id("value"), block()).signature(),
+        "[_,_]=(_)"
+    );
+}
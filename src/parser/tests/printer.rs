@@ -0,0 +1,122 @@
+use crate::parser::ast::{CompoundExpression, Expression};
+use crate::parser::tests::{parser, success};
+
+fn expression(src: &str) -> Expression<'_> {
+    let p = parser(src);
+    success(p.validate_eof(p.expression(0)))
+}
+
+fn round_trip(src: &str) -> String {
+    let expr = expression(src);
+    let printed = expr.to_wren_source();
+    assert_eq!(expression(&printed), expr, "{} printed as {}", src, printed);
+    printed
+}
+
+#[test]
+fn arithmetic_test() {
+    let arith = |src| success(parser(src).arith(0)).to_wren_source();
+    assert_eq!(arith("* 1"), "* 1");
+    assert_eq!(arith("- -id"), "- -id");
+    assert_eq!(arith("+ 1 * 2 - (x / 5)"), "+ 1 * 2 - x / 5");
+    assert_eq!(arith("...4"), "... 4");
+    assert_eq!(arith("<< 2"), "<< 2");
+    assert_eq!(arith("^ y"), "^ y");
+
+    match expression("(1 + 2 * x) + id") {
+        Expression::Compound(_, tail) => match tail.as_ref() {
+            CompoundExpression::Arith(a) => assert_eq!(a.to_wren_source(), "+ id"),
+            other => panic!("{:?}", other),
+        },
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn arithmetic_round_trip_test() {
+    let sources = [
+        "1 + 2",
+        "1 + 2 * 3",
+        "a * 1",
+        "a / 2",
+        "a + 3",
+        "a .. 4",
+        "a | 1",
+        "a - -id",
+        "a + 1 * 2 - (x / 5)",
+        "x + 0",
+        "x - 0",
+        "x + 0.0",
+        "x * 1.0",
+        "x * 0x0",
+        "x.y(1) * 0",
+        "a + x * 0",
+        "(a + b) * 1",
+        "x * 1 * 1 + 0",
+        "x % 3 & 1 ^ y >> 2 << z",
+        "1.5 * -2.5",
+        "- - x",
+        "- 1",
+    ];
+    for src in sources {
+        round_trip(src);
+    }
+    assert_eq!(round_trip("(a + b) * c"), "(a + b) * c");
+    assert_eq!(round_trip("a * (b + c)"), "a * b + c");
+    assert_eq!(round_trip("((a))"), "a");
+}
+
+#[test]
+fn expression_round_trip_test() {
+    let sources = [
+        "!x",
+        "!(1 + 2 * x)",
+        "(!a) + b",
+        "id + 1 || true && false",
+        "a > 1 && b.size < c[0] || f(d)",
+        "[1, x + 0, !!y]",
+        "{x * 1 : true && y, \"k\": null}",
+        "f(x * 1, !!y)",
+        "a ? x + 0 : !!b",
+        "x is Num && true",
+        "1..2",
+        "lo...hi.count",
+        "list.map { |x| x * 2 }.where { |x| x > 0 }.toList",
+        "Fiber.new {\n  var a = 1\n  a = a + 1\n  return a\n}",
+        "f { |a, b| if (a > b) a = b else { a = 1 b = 2 } }",
+        "'c' + \"s\"",
+        "import \"module\" for A, B as C",
+        "(a + b).c",
+        "x[0] + y[i + 1]",
+    ];
+    for src in sources {
+        round_trip(src);
+    }
+    assert_eq!(round_trip("!(a && b)"), "!(a && b)");
+    assert_eq!(
+        round_trip("f {|x| var y = x\n return y*2 }"),
+        "f { |x|\n  var y = x\n  return y * 2\n}"
+    );
+}
+
+
+#[test]
+fn minimal_parens_test() {
+    let print = |src| {
+        let printed = expression(src).to_wren_source();
+        assert_eq!(expression(&printed).to_wren_source(), printed, "{}", src);
+        printed
+    };
+    assert_eq!(print("(a * b) + c"), "a * b + c");
+    assert_eq!(print("(1 + 2 * x) + id"), "1 + 2 * x + id");
+    assert_eq!(print("(a - b) - (c * d) / e"), "a - b - c * d / e");
+    assert_eq!(print("(a && b) || c"), "a && b || c");
+    assert_eq!(print("(a == b) ? x : y"), "a == b ? x : y");
+    assert_eq!(print("(a + b) * c"), "(a + b) * c");
+    assert_eq!(print("(a * b + c) * d"), "(a * b + c) * d");
+    assert_eq!(print("(a || b) && c"), "(a || b) && c");
+    assert_eq!(print("(x ? a : b) ? c : d"), "(x ? a : b) ? c : d");
+    assert_eq!(print("(a | b) & c"), "(a | b) & c");
+    assert_eq!(print("(a + 1).b"), "(a + 1).b");
+    assert_eq!(print("(!a) + b"), "(!a) + b");
+}
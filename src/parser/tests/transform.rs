@@ -0,0 +1,190 @@
+use crate::parser::ast::{AtomExpression, Expression, Id, Number, Script, Unit};
+use crate::parser::tests::{parser, success};
+use crate::parser::transform::{
+    merge, rename_class, rename_function, reorder_units, simplify, transform_expressions,
+};
+
+fn expression(src: &str) -> Expression<'_> {
+    success(parser(src).expression(0))
+}
+
+fn script(src: &str) -> Script<'_> {
+    success(parser(src).script(0))
+}
+
+fn simplifies(src: &str, expected: &str) {
+    let simplified = simplify(expression(src));
+    assert_eq!(simplified, expression(expected), "{}", src);
+    assert_eq!(simplify(simplified.clone()), simplified, "{} is not idempotent", src);
+}
+
+#[test]
+fn simplify_test() {
+    simplifies("!!x", "x");
+    simplifies("!!!!x", "x");
+    simplifies("!!!x", "!x");
+    simplifies("x + 0", "x");
+    simplifies("x - 0", "x");
+    simplifies("x + 0.0", "x");
+    simplifies("x * 1", "x");
+    simplifies("x * 1.0", "x");
+    simplifies("x * 0", "0");
+    simplifies("x * 0x0", "0x0");
+    simplifies("x.y(1) * 0", "0");
+    simplifies("true && x", "x");
+    simplifies("false && x", "false");
+    simplifies("true || x", "true");
+    simplifies("true && false && x", "false");
+    simplifies("true && true || x", "true");
+    simplifies("a + x * 0", "a");
+    simplifies("a + x * 1", "a + x");
+    simplifies("(a + b) * 1", "a + b");
+    simplifies("x * 1 * 1 + 0", "x");
+    simplifies("!!(x + 0)", "x");
+    simplifies("[x + 0, !!y]", "[x, y]");
+    simplifies("{x * 1 : true && y}", "{x : y}");
+    simplifies("f(x * 1, !!y)", "f(x, y)");
+    simplifies("a ? x + 0 : !!b", "a ? x : b");
+    simplifies("x is Num && true", "x is Num && true");
+}
+
+#[test]
+fn simplify_keeps_semantics_test() {
+    simplifies("false && x || y", "false && x || y");
+    simplifies("true || x ? a : b", "true || x ? a : b");
+    simplifies("false && x && y", "false");
+    simplifies("true || x || y", "true");
+    simplifies("x * 2", "x * 2");
+    simplifies("x / 1", "x / 1");
+    simplifies("x + 1", "x + 1");
+    simplifies("false || x", "false || x");
+}
+
+#[test]
+fn simplify_method_test() {
+    assert_eq!(expression("!!(x * 1)").simplify(), expression("x"));
+    assert_eq!(expression("true && x").simplify(), expression("x"));
+}
+
+const TREES: &str = r#"
+    class Tree {
+      construct new(item, depth) {
+        _item = item
+        if (depth > 0) {
+          _left = Tree.new(item - 1, depth - 1)
+        }
+      }
+      check() {
+        return _left.check() + Tree.size
+      }
+    }
+    class Forest is Tree {
+      plant(x) {
+        var trees = [Tree.new(x, 1), Tree.new(x, 2)]
+        return x is Tree
+      }
+    }
+    var tree = Tree.new(1, 2)
+    tree.check()
+"#;
+
+#[test]
+fn rename_class_test() {
+    let renamed = rename_class(script(TREES), "Tree", "Node");
+    assert_eq!(renamed, script(&TREES.replace("Tree", "Node")));
+    assert_eq!(script(TREES).rename_class("Tree", "Node"), renamed);
+    assert_eq!(rename_class(script(TREES), "Missing", "Node"), script(TREES));
+}
+
+#[test]
+fn rename_function_test() {
+    let renamed = rename_function(script(TREES), "check", "verify");
+    assert_eq!(renamed, script(&TREES.replace("check", "verify")));
+
+    let renamed = script(TREES).rename_function("plant", "grow");
+    assert!(renamed.get_class("Forest").is_some());
+    assert_eq!(renamed, script(&TREES.replace("plant", "grow")));
+}
+
+#[test]
+fn reorder_units_test() {
+    let src = r#"
+        var a = 1
+        class B {}
+        f(x) { return x }
+        import "m" for M
+        { var b = 2 }
+        class A is B {}
+        import "n"
+        g() { return 1 }
+        a = a + 1
+    "#;
+    let expected = r#"
+        import "m" for M
+        import "n"
+        class B {}
+        class A is B {}
+        f(x) { return x }
+        g() { return 1 }
+        var a = 1
+        { var b = 2 }
+        a = a + 1
+    "#;
+    assert_eq!(script(src).units.len(), 9);
+    assert_eq!(reorder_units(script(src)), script(expected));
+    assert_eq!(script(expected).reorder_units(), script(expected));
+}
+
+#[test]
+fn merge_test() {
+    let left = script("import \"m\" for A\nclass B {}\nimport \"n\"");
+    let right = script("import \"m\" for A, C as D\nvar b = B.new()\nimport \"o\" for E");
+    let expected = r#"
+        import "m" for A, C as D
+        class B {}
+        import "n"
+        var b = B.new()
+        import "o" for E
+    "#;
+    assert_eq!(merge(left.clone(), right.clone()), script(expected));
+    assert_eq!(left.merge(right).imports().count(), 3);
+    assert_eq!(merge(script("var a = 1"), Script { units: vec![] }), script("var a = 1"));
+}
+
+#[test]
+fn transform_test() {
+    let src = "class A {}\nclass B is A {}\nvar a = 1";
+    let expected = "class A is Object {}\nclass B is A {}\nvar a = 1";
+    let transformed = script(src).transform(|unit| match unit {
+        Unit::Class(mut c) if c.inherit.is_none() => {
+            c.inherit = Some(Id { value: "Object" });
+            Unit::Class(c)
+        }
+        unit => unit,
+    });
+    assert_eq!(transformed, script(expected));
+}
+
+#[test]
+fn transform_expressions_test() {
+    let src = "var a = 1 + f(1, [1])\nfor (x in 1..3) { System.print(x * 1) }";
+    let expected = "var a = 2 + f(2, [2])\nfor (x in 1..3) { System.print(x * 2) }";
+    let bump = |e| match e {
+        Expression::Atom(AtomExpression::Number(Number::Int(1))) => {
+            Expression::Atom(AtomExpression::Number(Number::Int(2)))
+        }
+        e => e,
+    };
+    assert_eq!(transform_expressions(script(src), bump), script(expected));
+
+    let mut calls = 0;
+    let negated = script("x = a").transform_expressions(|e| {
+        calls += 1;
+        match e {
+            e @ Expression::Atom(_) => Expression::Not(Box::new(e)),
+            e => e,
+        }
+    });
+    assert_eq!(negated, script("!x = !a"));
+    assert_eq!(calls, 2);
+}
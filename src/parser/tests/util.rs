@@ -0,0 +1,30 @@
+use crate::parser::util::{line_column, strip_char_quotes, strip_quotes};
+
+#[test]
+fn strip_quotes_test() {
+    assert_eq!(strip_quotes("\"hello\""), "hello");
+    assert_eq!(strip_quotes("\"\""), "");
+    assert_eq!(strip_quotes("\"\"\" block \"\"\""), " block ");
+    assert_eq!(strip_quotes("\""), "\"");
+    assert_eq!(strip_quotes("plain"), "plain");
+}
+
+#[test]
+fn strip_char_quotes_test() {
+    assert_eq!(strip_char_quotes("'c'"), "c");
+    assert_eq!(strip_char_quotes("'"), "'");
+    assert_eq!(strip_char_quotes("c"), "c");
+}
+
+#[test]
+fn line_column_test() {
+    let src = "ab\ncd\n\nxyz";
+    assert_eq!(line_column(src, 0), (1, 1));
+    assert_eq!(line_column(src, 1), (1, 2));
+    assert_eq!(line_column(src, 3), (2, 1));
+    assert_eq!(line_column(src, 6), (3, 1));
+    assert_eq!(line_column(src, 9), (4, 3));
+    assert_eq!(line_column(src, 100), (4, 4));
+    assert_eq!(line_column("é\né", 4), (2, 1));
+    assert_eq!(line_column("é\né", 5), (2, 2));
+}
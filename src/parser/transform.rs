@@ -0,0 +1,332 @@
+use crate::parser::ast::*;
+use crate::parser::visitor::{walk_mut, NodeMut};
+use std::mem;
+
+type Rule = for<'a> fn(&Expression<'a>) -> Option<Expression<'a>>;
+
+const RULES: [Rule; 7] = [
+    double_not,
+    add_zero,
+    mul_one,
+    mul_zero,
+    true_and,
+    false_and,
+    true_or,
+];
+
+// Applies the rules bottom-up until none of them matches anymore, so the result is a fixpoint.
+pub fn simplify<'a>(expr: Expression<'a>) -> Expression<'a> {
+    let mut expr = simplify_children(expr);
+    while let Some(next) = RULES.iter().find_map(|rule| rule(&expr)) {
+        expr = next;
+    }
+    expr
+}
+
+// Class names are referenced by the head of a call chain (`Tree.new`, `x is Tree`),
+// so only the calls that start a chain are renamed.
+pub fn rename_class<'a>(mut script: Script<'a>, old_name: &str, new_name: &'a str) -> Script<'a> {
+    let rename = |id: &mut Id<'a>| {
+        if id.value == old_name {
+            id.value = new_name
+        }
+    };
+    walk_mut(NodeMut::Script(&mut script), |node| {
+        match node {
+            NodeMut::Class(c) => {
+                rename(&mut c.name);
+                c.inherit.iter_mut().for_each(rename);
+            }
+            NodeMut::Atom(AtomExpression::Call(c))
+            | NodeMut::Atom(AtomExpression::CollectionElem(c, _)) => rename(&mut c.id),
+            NodeMut::Atom(AtomExpression::Range(r)) => {
+                for e in [&mut r.left, &mut r.right] {
+                    if let RangeExpression::Call(c) = e {
+                        rename(&mut c.id)
+                    }
+                }
+            }
+            _ => (),
+        }
+        true
+    });
+    script
+}
+
+pub fn rename_function<'a>(
+    mut script: Script<'a>,
+    old_name: &str,
+    new_name: &'a str,
+) -> Script<'a> {
+    let rename = |id: &mut Id<'a>| {
+        if id.value == old_name {
+            id.value = new_name
+        }
+    };
+    walk_mut(NodeMut::Script(&mut script), |node| {
+        match node {
+            NodeMut::Function(f) => rename(&mut f.name),
+            NodeMut::Call(c) => rename(&mut c.id),
+            _ => (),
+        }
+        true
+    });
+    script
+}
+
+// Imports, classes, functions and then everything else; `sort_by_key` is stable,
+// so units of the same kind keep their order.
+pub fn reorder_units<'a>(mut script: Script<'a>) -> Script<'a> {
+    script.units.sort_by_key(|unit| match unit {
+        Unit::Import(_) => 0,
+        Unit::Class(_) => 1,
+        Unit::Fn(_) => 2,
+        Unit::Statement(_) | Unit::Block(_) => 3,
+    });
+    script
+}
+
+// The units of `right` follow those of `left`, except that an import of a module imported
+// before is merged into the first import of it.
+pub fn merge<'a>(left: Script<'a>, right: Script<'a>) -> Script<'a> {
+    let mut units: Vec<Unit<'a>> = vec![];
+    for unit in left.into_iter().chain(right) {
+        match unit {
+            Unit::Import(import) => {
+                let merged = units.iter_mut().find_map(|u| match u {
+                    Unit::Import(first) => {
+                        ImportModule::merge_variables(first.clone(), import.clone())
+                            .ok()
+                            .map(|m| *first = m)
+                    }
+                    _ => None,
+                });
+                if merged.is_none() {
+                    units.push(Unit::Import(import))
+                }
+            }
+            unit => units.push(unit),
+        }
+    }
+    Script { units }
+}
+
+pub fn transform<'a, F>(script: Script<'a>, f: F) -> Script<'a>
+where
+    F: FnMut(Unit<'a>) -> Unit<'a>,
+{
+    script.into_iter().map(f).collect()
+}
+
+// Bottom-up, so `f` sees an expression after its operands have been transformed
+// and is never called again on what it returned.
+pub fn transform_expressions<'a, F>(mut script: Script<'a>, mut f: F) -> Script<'a>
+where
+    F: FnMut(Expression<'a>) -> Expression<'a>,
+{
+    transform_node(NodeMut::Script(&mut script), &mut f);
+    script
+}
+
+fn transform_node<'a, F>(node: NodeMut<'_, 'a>, f: &mut F)
+where
+    F: FnMut(Expression<'a>) -> Expression<'a>,
+{
+    match node {
+        NodeMut::Expression(e) => {
+            for child in NodeMut::Expression(&mut *e).into_children() {
+                transform_node(child, f)
+            }
+            *e = f(mem::replace(e, Expression::E));
+        }
+        node => {
+            for child in node.into_children() {
+                transform_node(child, f)
+            }
+        }
+    }
+}
+
+fn simplify_children<'a>(expr: Expression<'a>) -> Expression<'a> {
+    match expr {
+        Expression::Atom(a) => Expression::Atom(simplify_atom(a)),
+        Expression::Compound(lhs, tail) => Expression::Compound(
+            Box::new(simplify(*lhs)),
+            Box::new(simplify_compound(*tail)),
+        ),
+        Expression::Not(e) => Expression::Not(Box::new(simplify(*e))),
+        Expression::E => Expression::E,
+    }
+}
+
+fn simplify_atom<'a>(atom: AtomExpression<'a>) -> AtomExpression<'a> {
+    match atom {
+        AtomExpression::MapInit(pairs) => AtomExpression::MapInit(
+            pairs
+                .into_iter()
+                .map(|(k, v)| (simplify(k), simplify(v)))
+                .collect(),
+        ),
+        AtomExpression::ListInit(e) => AtomExpression::ListInit(simplify_enum(e)),
+        AtomExpression::Call(c) => AtomExpression::Call(simplify_call(c)),
+        AtomExpression::CollectionElem(c, e) => {
+            AtomExpression::CollectionElem(simplify_call(c), simplify_enum(e))
+        }
+        AtomExpression::Sub(a) => AtomExpression::Sub(Box::new(simplify_atom(*a))),
+        other => other,
+    }
+}
+
+fn simplify_enum(e: Enumeration) -> Enumeration {
+    Enumeration::new(e.values.into_iter().map(simplify).collect())
+}
+
+fn simplify_call(call: Call) -> Call {
+    let middle = match call.middle {
+        BlockOrEnum::Enum(e) => BlockOrEnum::Enum(simplify_enum(e)),
+        other => other,
+    };
+    Call {
+        id: call.id,
+        tail: call.tail.map(|t| Box::new(simplify_call(*t))),
+        middle,
+    }
+}
+
+fn simplify_compound(tail: CompoundExpression) -> CompoundExpression {
+    match tail {
+        CompoundExpression::Logic(l) => CompoundExpression::Logic(simplify_logic(l)),
+        CompoundExpression::Arith(a) => CompoundExpression::Arith(simplify_arith(a)),
+        CompoundExpression::Tail(c) => CompoundExpression::Tail(simplify_call(c)),
+        CompoundExpression::Is(e) => CompoundExpression::Is(Box::new(simplify(*e))),
+        CompoundExpression::Elvis(e) => CompoundExpression::Elvis(Elvis {
+            lhs: simplify(e.lhs),
+            rhs: simplify(e.rhs),
+        }),
+    }
+}
+
+fn simplify_logic<'a>(logic: Logic<'a>) -> Logic<'a> {
+    let simplify_tail = |tail: Vec<(Expression<'a>, Box<Logic<'a>>)>| {
+        tail.into_iter()
+            .map(|(e, l)| (simplify(e), Box::new(simplify_logic(*l))))
+            .collect()
+    };
+    match logic {
+        Logic::Atom(op, e) => Logic::Atom(op, simplify(e)),
+        Logic::And(l, tail) => Logic::And(Box::new(simplify_logic(*l)), simplify_tail(tail)),
+        Logic::Or(l, tail) => Logic::Or(Box::new(simplify_logic(*l)), simplify_tail(tail)),
+    }
+}
+
+fn simplify_arith(arith: Arithmetic) -> Arithmetic {
+    match arith {
+        Arithmetic::Expression(e) => Arithmetic::Expression(simplify(e)),
+        Arithmetic::Mul(sign, e) => Arithmetic::Mul(sign, simplify(e)),
+        Arithmetic::Add(sign, a) => Arithmetic::Add(sign, Box::new(simplify_arith(*a))),
+        Arithmetic::Range(sign, a) => Arithmetic::Range(sign, Box::new(simplify_arith(*a))),
+        Arithmetic::Shift(sign, a) => Arithmetic::Shift(sign, Box::new(simplify_arith(*a))),
+        Arithmetic::Bit(sign, a) => Arithmetic::Bit(sign, Box::new(simplify_arith(*a))),
+    }
+}
+
+fn double_not<'a>(expr: &Expression<'a>) -> Option<Expression<'a>> {
+    match expr {
+        Expression::Not(e) => match e.as_ref() {
+            Expression::Not(e) => Some(e.as_ref().clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn add_zero<'a>(expr: &Expression<'a>) -> Option<Expression<'a>> {
+    match expr {
+        Expression::Compound(lhs, tail) => match tail.as_ref() {
+            CompoundExpression::Arith(Arithmetic::Add(_, a)) => match a.as_ref() {
+                Arithmetic::Expression(e) if is_number(e, 0.0) => Some(lhs.as_ref().clone()),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn mul_one<'a>(expr: &Expression<'a>) -> Option<Expression<'a>> {
+    match expr {
+        Expression::Compound(lhs, tail) => match tail.as_ref() {
+            CompoundExpression::Arith(Arithmetic::Mul(MulSign::Mul, e)) if is_number(e, 1.0) => {
+                Some(lhs.as_ref().clone())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn mul_zero<'a>(expr: &Expression<'a>) -> Option<Expression<'a>> {
+    match expr {
+        Expression::Compound(_, tail) => match tail.as_ref() {
+            CompoundExpression::Arith(Arithmetic::Mul(MulSign::Mul, e)) if is_number(e, 0.0) => {
+                Some(e.clone())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn true_and<'a>(expr: &Expression<'a>) -> Option<Expression<'a>> {
+    match logic_with_bool(expr) {
+        Some((true, LogicOp::And, rhs)) => Some(rhs.clone()),
+        _ => None,
+    }
+}
+
+// The rhs of a compound holds the rest of the chain, so it can only be dropped
+// when it doesn't contain an operator that binds weaker than the current one.
+fn false_and<'a>(expr: &Expression<'a>) -> Option<Expression<'a>> {
+    match logic_with_bool(expr) {
+        Some((false, LogicOp::And, rhs)) if binds_tighter(rhs, LogicOp::And) => {
+            Some(AtomExpression::from(false).into())
+        }
+        _ => None,
+    }
+}
+
+fn true_or<'a>(expr: &Expression<'a>) -> Option<Expression<'a>> {
+    match logic_with_bool(expr) {
+        Some((true, LogicOp::Or, rhs)) if binds_tighter(rhs, LogicOp::Or) => {
+            Some(AtomExpression::from(true).into())
+        }
+        _ => None,
+    }
+}
+
+fn logic_with_bool<'s, 'a>(
+    expr: &'s Expression<'a>,
+) -> Option<(bool, &'s LogicOp, &'s Expression<'a>)> {
+    match expr {
+        Expression::Compound(lhs, tail) => match (lhs.as_ref(), tail.as_ref()) {
+            (
+                Expression::Atom(AtomExpression::Bool(v)),
+                CompoundExpression::Logic(Logic::Atom(op, rhs)),
+            ) => Some((*v, op, rhs)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn binds_tighter(expr: &Expression, op: LogicOp) -> bool {
+    let prec = CompoundExpression::Logic(Logic::Atom(op, Expression::E)).operator_precedence();
+    expr.loosest_precedence() >= prec
+}
+
+fn is_number(expr: &Expression, value: f64) -> bool {
+    match expr {
+        Expression::Atom(AtomExpression::Number(n)) => n.as_f64() == value,
+        _ => false,
+    }
+}
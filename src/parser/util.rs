@@ -0,0 +1,30 @@
+pub fn strip_quotes(s: &str) -> &str {
+    strip(s, "\"\"\"")
+        .or_else(|| strip(s, "\""))
+        .unwrap_or(s)
+}
+
+pub fn strip_char_quotes(s: &str) -> &str {
+    strip(s, "'").unwrap_or(s)
+}
+
+fn strip<'a>(s: &'a str, quote: &str) -> Option<&'a str> {
+    if s.len() < quote.len() * 2 {
+        return None;
+    }
+    s.strip_prefix(quote).and_then(|s| s.strip_suffix(quote))
+}
+
+// 1-indexed line and column (in chars) of a byte offset, clamped to the end of the source.
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
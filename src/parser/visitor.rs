@@ -0,0 +1,399 @@
+use crate::parser::ast::*;
+
+#[derive(Debug, Copy, Clone)]
+pub enum Node<'s, 'a> {
+    Script(&'s Script<'a>),
+    Unit(&'s Unit<'a>),
+    Class(&'s ClassDefinition<'a>),
+    ClassUnit(&'s ClassUnit<'a>),
+    Function(&'s Function<'a>),
+    Import(&'s ImportModule<'a>),
+    Statement(&'s Statement<'a>),
+    Block(&'s Block<'a>),
+    Expression(&'s Expression<'a>),
+    Atom(&'s AtomExpression<'a>),
+    Compound(&'s CompoundExpression<'a>),
+    Logic(&'s Logic<'a>),
+    Arith(&'s Arithmetic<'a>),
+    Call(&'s Call<'a>),
+    Assignment(&'s Assignment<'a>),
+    If(&'s If<'a>),
+    While(&'s While<'a>),
+    For(&'s For<'a>),
+}
+
+impl<'s, 'a> Node<'s, 'a> {
+    fn body(body: &'s BlockOrStatement<'a>) -> Node<'s, 'a> {
+        match body {
+            BlockOrStatement::Block(b) => Node::Block(b),
+            BlockOrStatement::Single(s) => Node::Statement(s),
+        }
+    }
+
+    pub fn children(&self) -> Vec<Node<'s, 'a>> {
+        match *self {
+            Node::Script(s) => s.units.iter().map(Node::Unit).collect(),
+            Node::Unit(u) => vec![match u {
+                Unit::Class(c) => Node::Class(c),
+                Unit::Fn(f) => Node::Function(f),
+                Unit::Import(i) => Node::Import(i),
+                Unit::Statement(s) => Node::Statement(s),
+                Unit::Block(b) => Node::Block(b),
+            }],
+            Node::Class(c) => attribute_atoms(&c.attributes)
+                .chain(c.elems.iter().map(Node::ClassUnit))
+                .collect(),
+            Node::ClassUnit(u) => {
+                let mut children: Vec<_> = attribute_atoms(&u.attributes).collect();
+                match &u.statement {
+                    ClassStatement::Fn(f) => children.push(Node::Function(f)),
+                    ClassStatement::OpGetter(_, b) => children.extend(b.iter().map(Node::Block)),
+                    ClassStatement::Setter(_, _, b)
+                    | ClassStatement::OpSetter(_, _, b)
+                    | ClassStatement::Constructor(_, _, b) => children.push(Node::Block(b)),
+                    ClassStatement::SubscriptGet(e, b) | ClassStatement::SubscriptSet(e, _, b) => {
+                        children.extend(e.values.iter().map(Node::Expression));
+                        children.push(Node::Block(b))
+                    }
+                }
+                children
+            }
+            Node::Function(f) => f.block.iter().map(Node::Block).collect(),
+            Node::Import(_) => vec![],
+            Node::Statement(s) => match s {
+                Statement::Expression(e) | Statement::Return(e) => vec![Node::Expression(e)],
+                Statement::Assignment(a) => vec![Node::Assignment(a)],
+                Statement::AssignmentNull(_) => vec![],
+                Statement::If(i) => vec![Node::If(i)],
+                Statement::While(w) => vec![Node::While(w)],
+                Statement::For(f) => vec![Node::For(f)],
+                Statement::Block(b) => vec![Node::Block(b)],
+            },
+            Node::Block(b) => b.statements.iter().map(Node::Statement).collect(),
+            Node::Expression(e) => match e {
+                Expression::Atom(a) => vec![Node::Atom(a)],
+                Expression::Compound(e, c) => vec![Node::Expression(e), Node::Compound(c)],
+                Expression::Not(e) => vec![Node::Expression(e)],
+                Expression::E => vec![],
+            },
+            Node::Atom(a) => match a {
+                AtomExpression::MapInit(pairs) => pairs
+                    .iter()
+                    .flat_map(|(k, v)| [Node::Expression(k), Node::Expression(v)])
+                    .collect(),
+                AtomExpression::ListInit(e) => e.values.iter().map(Node::Expression).collect(),
+                AtomExpression::Call(c) => vec![Node::Call(c)],
+                AtomExpression::Range(r) => [&r.left, &r.right]
+                    .into_iter()
+                    .filter_map(|e| match e {
+                        RangeExpression::Call(c) => Some(Node::Call(c)),
+                        RangeExpression::Num(_) => None,
+                    })
+                    .collect(),
+                AtomExpression::CollectionElem(c, e) => [Node::Call(c)]
+                    .into_iter()
+                    .chain(e.values.iter().map(Node::Expression))
+                    .collect(),
+                AtomExpression::ImportModule(i) => vec![Node::Import(i)],
+                AtomExpression::Sub(a) => vec![Node::Atom(a)],
+                _ => vec![],
+            },
+            Node::Compound(c) => match c {
+                CompoundExpression::Logic(l) => vec![Node::Logic(l)],
+                CompoundExpression::Arith(a) => vec![Node::Arith(a)],
+                CompoundExpression::Tail(c) => vec![Node::Call(c)],
+                CompoundExpression::Is(e) => vec![Node::Expression(e)],
+                CompoundExpression::Elvis(e) => {
+                    vec![Node::Expression(&e.lhs), Node::Expression(&e.rhs)]
+                }
+            },
+            Node::Logic(l) => match l {
+                Logic::Atom(_, e) => vec![Node::Expression(e)],
+                Logic::And(l, tail) | Logic::Or(l, tail) => [Node::Logic(l)]
+                    .into_iter()
+                    .chain(
+                        tail.iter()
+                            .flat_map(|(e, l)| [Node::Expression(e), Node::Logic(l)]),
+                    )
+                    .collect(),
+            },
+            Node::Arith(a) => match a {
+                Arithmetic::Expression(e) | Arithmetic::Mul(_, e) => vec![Node::Expression(e)],
+                Arithmetic::Add(_, a)
+                | Arithmetic::Range(_, a)
+                | Arithmetic::Shift(_, a)
+                | Arithmetic::Bit(_, a) => vec![Node::Arith(a)],
+            },
+            Node::Call(c) => {
+                let mut children = match &c.middle {
+                    BlockOrEnum::Block(b) => vec![Node::Block(b)],
+                    BlockOrEnum::Enum(e) => e.values.iter().map(Node::Expression).collect(),
+                    BlockOrEnum::None => vec![],
+                };
+                children.extend(c.tail.iter().map(|t| Node::Call(t)));
+                children
+            }
+            Node::Assignment(a) => {
+                let rhs = match a.rhs.as_ref() {
+                    Rhs::Expression(e) => vec![Node::Expression(e)],
+                    Rhs::Assignment(a) => vec![Node::Assignment(a)],
+                    Rhs::Assignments(v) => v.iter().map(Node::Assignment).collect(),
+                };
+                [Node::Expression(&a.lhs)].into_iter().chain(rhs).collect()
+            }
+            Node::If(i) => std::iter::once(&i.main)
+                .chain(i.others.iter())
+                .flat_map(|b| [Node::Expression(&b.cond), Node::body(&b.action)])
+                .chain(i.els.iter().map(Node::body))
+                .collect(),
+            Node::While(w) => {
+                let cond = match &w.cond {
+                    WhileCond::Expression(e) => Node::Expression(e),
+                    WhileCond::Assignment(a) => Node::Assignment(a),
+                };
+                vec![cond, Node::body(&w.body)]
+            }
+            Node::For(f) => vec![Node::Expression(&f.collection), Node::body(&f.body)],
+        }
+    }
+
+    pub(crate) fn is_nesting(&self) -> bool {
+        matches!(
+            self,
+            Node::Expression(_) | Node::Statement(_) | Node::Block(_)
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum NodeMut<'s, 'a> {
+    Script(&'s mut Script<'a>),
+    Unit(&'s mut Unit<'a>),
+    Class(&'s mut ClassDefinition<'a>),
+    ClassUnit(&'s mut ClassUnit<'a>),
+    Function(&'s mut Function<'a>),
+    Import(&'s mut ImportModule<'a>),
+    Statement(&'s mut Statement<'a>),
+    Block(&'s mut Block<'a>),
+    Expression(&'s mut Expression<'a>),
+    Atom(&'s mut AtomExpression<'a>),
+    Compound(&'s mut CompoundExpression<'a>),
+    Logic(&'s mut Logic<'a>),
+    Arith(&'s mut Arithmetic<'a>),
+    Call(&'s mut Call<'a>),
+    Assignment(&'s mut Assignment<'a>),
+    If(&'s mut If<'a>),
+    While(&'s mut While<'a>),
+    For(&'s mut For<'a>),
+}
+
+impl<'s, 'a> NodeMut<'s, 'a> {
+    fn body(body: &'s mut BlockOrStatement<'a>) -> NodeMut<'s, 'a> {
+        match body {
+            BlockOrStatement::Block(b) => NodeMut::Block(b),
+            BlockOrStatement::Single(s) => NodeMut::Statement(s),
+        }
+    }
+
+    pub fn into_children(self) -> Vec<NodeMut<'s, 'a>> {
+        match self {
+            NodeMut::Script(s) => s.units.iter_mut().map(NodeMut::Unit).collect(),
+            NodeMut::Unit(u) => vec![match u {
+                Unit::Class(c) => NodeMut::Class(c),
+                Unit::Fn(f) => NodeMut::Function(f),
+                Unit::Import(i) => NodeMut::Import(i),
+                Unit::Statement(s) => NodeMut::Statement(s),
+                Unit::Block(b) => NodeMut::Block(b),
+            }],
+            NodeMut::Class(c) => attribute_atoms_mut(&mut c.attributes)
+                .chain(c.elems.iter_mut().map(NodeMut::ClassUnit))
+                .collect(),
+            NodeMut::ClassUnit(u) => {
+                let mut children: Vec<_> = attribute_atoms_mut(&mut u.attributes).collect();
+                match &mut u.statement {
+                    ClassStatement::Fn(f) => children.push(NodeMut::Function(f)),
+                    ClassStatement::OpGetter(_, b) => {
+                        children.extend(b.iter_mut().map(NodeMut::Block))
+                    }
+                    ClassStatement::Setter(_, _, b)
+                    | ClassStatement::OpSetter(_, _, b)
+                    | ClassStatement::Constructor(_, _, b) => children.push(NodeMut::Block(b)),
+                    ClassStatement::SubscriptGet(e, b) | ClassStatement::SubscriptSet(e, _, b) => {
+                        children.extend(e.values.iter_mut().map(NodeMut::Expression));
+                        children.push(NodeMut::Block(b))
+                    }
+                }
+                children
+            }
+            NodeMut::Function(f) => f.block.iter_mut().map(NodeMut::Block).collect(),
+            NodeMut::Import(_) => vec![],
+            NodeMut::Statement(s) => match s {
+                Statement::Expression(e) | Statement::Return(e) => vec![NodeMut::Expression(e)],
+                Statement::Assignment(a) => vec![NodeMut::Assignment(a)],
+                Statement::AssignmentNull(_) => vec![],
+                Statement::If(i) => vec![NodeMut::If(i)],
+                Statement::While(w) => vec![NodeMut::While(w)],
+                Statement::For(f) => vec![NodeMut::For(f)],
+                Statement::Block(b) => vec![NodeMut::Block(b)],
+            },
+            NodeMut::Block(b) => b.statements.iter_mut().map(NodeMut::Statement).collect(),
+            NodeMut::Expression(e) => match e {
+                Expression::Atom(a) => vec![NodeMut::Atom(a)],
+                Expression::Compound(e, c) => vec![NodeMut::Expression(e), NodeMut::Compound(c)],
+                Expression::Not(e) => vec![NodeMut::Expression(e)],
+                Expression::E => vec![],
+            },
+            NodeMut::Atom(a) => match a {
+                AtomExpression::MapInit(pairs) => pairs
+                    .iter_mut()
+                    .flat_map(|(k, v)| [NodeMut::Expression(k), NodeMut::Expression(v)])
+                    .collect(),
+                AtomExpression::ListInit(e) => {
+                    e.values.iter_mut().map(NodeMut::Expression).collect()
+                }
+                AtomExpression::Call(c) => vec![NodeMut::Call(c)],
+                AtomExpression::Range(r) => [&mut r.left, &mut r.right]
+                    .into_iter()
+                    .filter_map(|e| match e {
+                        RangeExpression::Call(c) => Some(NodeMut::Call(c)),
+                        RangeExpression::Num(_) => None,
+                    })
+                    .collect(),
+                AtomExpression::CollectionElem(c, e) => [NodeMut::Call(c)]
+                    .into_iter()
+                    .chain(e.values.iter_mut().map(NodeMut::Expression))
+                    .collect(),
+                AtomExpression::ImportModule(i) => vec![NodeMut::Import(i)],
+                AtomExpression::Sub(a) => vec![NodeMut::Atom(a)],
+                _ => vec![],
+            },
+            NodeMut::Compound(c) => match c {
+                CompoundExpression::Logic(l) => vec![NodeMut::Logic(l)],
+                CompoundExpression::Arith(a) => vec![NodeMut::Arith(a)],
+                CompoundExpression::Tail(c) => vec![NodeMut::Call(c)],
+                CompoundExpression::Is(e) => vec![NodeMut::Expression(e)],
+                CompoundExpression::Elvis(e) => {
+                    vec![NodeMut::Expression(&mut e.lhs), NodeMut::Expression(&mut e.rhs)]
+                }
+            },
+            NodeMut::Logic(l) => match l {
+                Logic::Atom(_, e) => vec![NodeMut::Expression(e)],
+                Logic::And(l, tail) | Logic::Or(l, tail) => [NodeMut::Logic(l)]
+                    .into_iter()
+                    .chain(
+                        tail.iter_mut()
+                            .flat_map(|(e, l)| [NodeMut::Expression(e), NodeMut::Logic(l)]),
+                    )
+                    .collect(),
+            },
+            NodeMut::Arith(a) => match a {
+                Arithmetic::Expression(e) | Arithmetic::Mul(_, e) => vec![NodeMut::Expression(e)],
+                Arithmetic::Add(_, a)
+                | Arithmetic::Range(_, a)
+                | Arithmetic::Shift(_, a)
+                | Arithmetic::Bit(_, a) => vec![NodeMut::Arith(a)],
+            },
+            NodeMut::Call(c) => {
+                let mut children = match &mut c.middle {
+                    BlockOrEnum::Block(b) => vec![NodeMut::Block(b)],
+                    BlockOrEnum::Enum(e) => e.values.iter_mut().map(NodeMut::Expression).collect(),
+                    BlockOrEnum::None => vec![],
+                };
+                children.extend(c.tail.iter_mut().map(|t| NodeMut::Call(t)));
+                children
+            }
+            NodeMut::Assignment(a) => {
+                let rhs = match a.rhs.as_mut() {
+                    Rhs::Expression(e) => vec![NodeMut::Expression(e)],
+                    Rhs::Assignment(a) => vec![NodeMut::Assignment(a)],
+                    Rhs::Assignments(v) => v.iter_mut().map(NodeMut::Assignment).collect(),
+                };
+                [NodeMut::Expression(&mut a.lhs)]
+                    .into_iter()
+                    .chain(rhs)
+                    .collect()
+            }
+            NodeMut::If(i) => std::iter::once(&mut i.main)
+                .chain(i.others.iter_mut())
+                .flat_map(|b| {
+                    [
+                        NodeMut::Expression(&mut b.cond),
+                        NodeMut::body(&mut b.action),
+                    ]
+                })
+                .chain(i.els.iter_mut().map(NodeMut::body))
+                .collect(),
+            NodeMut::While(w) => {
+                let cond = match &mut w.cond {
+                    WhileCond::Expression(e) => NodeMut::Expression(e),
+                    WhileCond::Assignment(a) => NodeMut::Assignment(a),
+                };
+                vec![cond, NodeMut::body(&mut w.body)]
+            }
+            NodeMut::For(f) => vec![
+                NodeMut::Expression(&mut f.collection),
+                NodeMut::body(&mut f.body),
+            ],
+        }
+    }
+}
+
+fn attribute_atoms_mut<'s, 'a>(
+    attrs: &'s mut [Attribute<'a>],
+) -> impl Iterator<Item = NodeMut<'s, 'a>> {
+    attrs
+        .iter_mut()
+        .flat_map(|a| match a {
+            Attribute::Simple(_, v) => std::slice::from_mut(v),
+            Attribute::Group(_, _, vs) => vs.as_mut_slice(),
+        })
+        .filter_map(|v| v.expr.as_mut().map(NodeMut::Atom))
+}
+
+fn attribute_atoms<'s, 'a>(
+    attrs: &'s [Attribute<'a>],
+) -> impl Iterator<Item = Node<'s, 'a>> {
+    attrs
+        .iter()
+        .flat_map(|a| a.values())
+        .filter_map(|v| v.expr.as_ref().map(Node::Atom))
+}
+
+// Pre-order walk. Returning false from the visitor skips the children of that node.
+pub fn walk<'s, 'a, F>(node: Node<'s, 'a>, mut visit: F)
+where
+    F: FnMut(Node<'s, 'a>) -> bool,
+{
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        if visit(node) {
+            stack.extend(node.children().into_iter().rev());
+        }
+    }
+}
+
+// Mutable pre-order walk. The children are taken after the visitor has run,
+// so a node replaced by the visitor is traversed in its new shape.
+pub fn walk_mut<'s, 'a, F>(node: NodeMut<'s, 'a>, mut visit: F)
+where
+    F: FnMut(&mut NodeMut<'s, 'a>) -> bool,
+{
+    let mut stack = vec![node];
+    while let Some(mut node) = stack.pop() {
+        if visit(&mut node) {
+            stack.extend(node.into_children().into_iter().rev());
+        }
+    }
+}
+
+// Maximum nesting of expressions, statements and blocks below (and including) the node.
+pub fn depth(node: Node) -> usize {
+    let mut stack = vec![(node, 0)];
+    let mut max = 0;
+    while let Some((node, depth)) = stack.pop() {
+        let depth = if node.is_nesting() { depth + 1 } else { depth };
+        max = max.max(depth);
+        stack.extend(node.children().into_iter().map(|c| (c, depth)));
+    }
+    max
+}